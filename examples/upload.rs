@@ -0,0 +1,39 @@
+// Uploads a small text item to the arseeding testnet and pays for it with
+// everpay testnet tokens:
+//
+//     cargo run --example upload
+//
+// Items are signed with the throwaway keypair of tests/fixtures, or the one
+// ARWEAVE_KEYFILE points to. Its everpay testnet account, printed first, must
+// hold some CURRENCY (default usdc) test tokens. Never use a mainnet key here.
+
+use arseeding_rust::arseeding_types::ASError;
+use arseeding_rust::client::{ASClient, TESTNET_ARSEEDING_URL};
+use arseeding_rust::everpay::ArweaveSigner;
+use arseeding_rust::everpay_types::{Signer, TESTNET_EVERPAY_URL};
+use arseeding_rust::tags;
+
+const TEST_KEYFILE: &str =
+    "tests/fixtures/arweave-key-7eV1qae4qVNqsNChg3Scdi-DpOLJPCogct4ixoq1WNg.json";
+
+#[tokio::main]
+async fn main() -> Result<(), ASError> {
+    let keyfile = std::env::var("ARWEAVE_KEYFILE").unwrap_or_else(|_| TEST_KEYFILE.to_string());
+    let currency = std::env::var("CURRENCY").unwrap_or_else(|_| "usdc".to_string());
+    let jwk = std::fs::read(keyfile)?;
+
+    let account = ArweaveSigner::from_keypair_bytes(&jwk)?.wallet_address()?;
+    println!("everpay testnet account: {}", account);
+
+    let c = ASClient::from_keypair_bytes(&jwk, TESTNET_ARSEEDING_URL, TESTNET_EVERPAY_URL).await?;
+    let mut tags = tags::content_type_tag(tags::CONTENT_TYPE_TEXT);
+    tags.insert("App-Name".to_string(), "arseeding-rust-example".to_string());
+
+    let item_id = c
+        .send_and_pay(&currency, &tags, b"hello from arseeding-rust".to_vec(), "")
+        .await?;
+    println!("paid for item {}", item_id);
+    println!("{}/{}", TESTNET_ARSEEDING_URL, item_id);
+
+    Ok(())
+}
@@ -1,10 +1,17 @@
 use chrono::{DateTime, TimeZone, Utc};
 use serde::{Deserialize, Deserializer};
 use serde_derive::Serialize;
+use reqwest::{Response, StatusCode};
+use rust_decimal::Decimal;
 use std::fmt::Display;
+use std::time::{Duration, SystemTime};
+use url::Url;
+
+use crate::everpay_types::SignerType;
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct APIErrorRes {
+    #[serde(alias = "message")]
     pub error: String,
 }
 
@@ -13,6 +20,10 @@ pub struct BundlerRes {
     pub bundler: String,
 }
 
+// Order created by a submission, to be paid before `payment_expired_time`.
+// Arseeding has no endpoint to cancel an order: to abandon one, don't pay it.
+// The gateway drops unpaid orders once they expire (see `is_expired`) and the
+// client keeps nothing about them.
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ItemSubmissionRes {
@@ -20,22 +31,79 @@ pub struct ItemSubmissionRes {
     pub bundler: String,
     pub currency: String,
     pub decimals: i64,
+    #[serde(deserialize_with = "string_or_number")]
     pub fee: String,
     pub payment_expired_time: i64,
     pub expected_block: i64,
+    // parts of `fee`, in the same units, when the gateway breaks it down
+    #[serde(default, deserialize_with = "option_string_or_number")]
+    pub base_fee: Option<String>,
+    #[serde(default, deserialize_with = "option_string_or_number")]
+    pub size_fee: Option<String>,
+    #[serde(default, deserialize_with = "option_string_or_number")]
+    pub service_fee: Option<String>,
+}
+
+impl ItemSubmissionRes {
+    // `payment_expired_time` is in unix seconds
+    pub fn payment_deadline(&self) -> Option<DateTime<Utc>> {
+        Utc.timestamp_opt(self.payment_expired_time, 0).single()
+    }
+
+    // time left to pay for the item, None once the deadline has passed
+    pub fn time_remaining(&self) -> Option<Duration> {
+        self.time_remaining_at(SystemTime::now())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.time_remaining().is_none()
+    }
+
+    fn time_remaining_at(&self, now: SystemTime) -> Option<Duration> {
+        let deadline = SystemTime::UNIX_EPOCH
+            + Duration::from_secs(u64::try_from(self.payment_expired_time).ok()?);
+        match deadline.duration_since(now) {
+            Ok(d) if !d.is_zero() => Some(d),
+            _ => None,
+        }
+    }
+}
+
+// Order recorded by `IdempotencyCache` before it's paid, along with the base
+// url of the node it was submitted to: the payment is checked and made there.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingOrder {
+    pub base_url: String,
+    pub order: ItemSubmissionRes,
 }
 
+// The gateway signs native data with its own key: the item's owner is the
+// bundler, not the uploader (see `ASClient::submit_native_owned`).
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SubmitNativeRes {
     pub item_id: String,
 }
 
+impl SubmitNativeRes {
+    // shareable link to the item's data on `gateway`, e.g.
+    // https://arseed.web3infra.dev/{item_id}
+    pub fn data_url(&self, gateway: &Url) -> Url {
+        let mut url = gateway.clone();
+        if let Ok(mut segments) = url.path_segments_mut() {
+            segments.pop_if_empty().push(&self.item_id);
+        }
+        url
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FeeRes {
     pub currency: String,
     pub decimals: i64,
+    #[serde(deserialize_with = "string_or_number")]
     pub final_fee: String,
 }
 
@@ -53,6 +121,7 @@ pub struct OrderRes {
     pub size: i64,
     pub currency: String,
     pub decimals: u8,
+    #[serde(deserialize_with = "string_or_number")]
     pub fee: String,
     pub payment_expired_time: i64,
     pub expected_block: i64,
@@ -61,6 +130,176 @@ pub struct OrderRes {
     pub on_chain_status: String,
 }
 
+impl OrderRes {
+    pub fn signer_type(&self) -> Option<SignerType> {
+        SignerType::from_ans104_code(self.sign_type as u16)
+    }
+
+    pub fn payment_status(&self) -> PaymentStatus {
+        PaymentStatus::from(self.payment_status.as_str())
+    }
+
+    pub fn on_chain_status(&self) -> OnChainStatus {
+        OnChainStatus::from(self.on_chain_status.as_str())
+    }
+}
+
+// One line summary for logs and CLIs, `item_id (usdc, paid, success, 1.2KB)`.
+// The alternate form (`{:#}`) adds the fee and the expected block.
+impl Display for OrderRes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({}, {}, {}, {})",
+            self.item_id,
+            self.currency.to_lowercase(),
+            self.payment_status(),
+            self.on_chain_status(),
+            human_size(self.size)
+        )?;
+        if f.alternate() {
+            let fee = crate::util::from_base_units(&self.fee, self.decimals as i64)
+                .map(|d| d.to_string())
+                .unwrap_or_else(|_| self.fee.clone());
+            write!(
+                f,
+                " fee {} {}, expected block {}",
+                fee,
+                self.currency.to_lowercase(),
+                self.expected_block
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// `size` bytes with a unit, one decimal past KB (e.g. 512B, 1.2KB, 3.0MB)
+fn human_size(size: i64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if size < 1024 {
+        return format!("{}B", size);
+    }
+
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1}{}", value, UNITS[unit])
+}
+
+// direction of the `order` query param of the orders endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "asc",
+            SortOrder::Desc => "desc",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum PaymentStatus {
+    Unpaid,
+    Paid,
+    // not paid before `payment_expired_time`, the item won't be bundled
+    Expired,
+    // a status this version doesn't know about
+    Unknown(String),
+}
+
+impl From<&str> for PaymentStatus {
+    fn from(s: &str) -> Self {
+        match s {
+            "unpaid" => PaymentStatus::Unpaid,
+            "paid" => PaymentStatus::Paid,
+            "expired" => PaymentStatus::Expired,
+            _ => PaymentStatus::Unknown(s.to_string()),
+        }
+    }
+}
+
+impl From<String> for PaymentStatus {
+    fn from(s: String) -> Self {
+        PaymentStatus::from(s.as_str())
+    }
+}
+
+impl From<PaymentStatus> for String {
+    fn from(s: PaymentStatus) -> Self {
+        s.to_string()
+    }
+}
+
+impl Display for PaymentStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PaymentStatus::Unpaid => write!(f, "unpaid"),
+            PaymentStatus::Paid => write!(f, "paid"),
+            PaymentStatus::Expired => write!(f, "expired"),
+            PaymentStatus::Unknown(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum OnChainStatus {
+    // paid, waiting to be bundled
+    Waiting,
+    // bundle posted to arweave, not mined yet
+    Pending,
+    Success,
+    Failed,
+    // a status this version doesn't know about
+    Unknown(String),
+}
+
+impl From<&str> for OnChainStatus {
+    fn from(s: &str) -> Self {
+        match s {
+            "waiting" => OnChainStatus::Waiting,
+            "pending" => OnChainStatus::Pending,
+            "success" => OnChainStatus::Success,
+            "failed" => OnChainStatus::Failed,
+            _ => OnChainStatus::Unknown(s.to_string()),
+        }
+    }
+}
+
+impl From<String> for OnChainStatus {
+    fn from(s: String) -> Self {
+        OnChainStatus::from(s.as_str())
+    }
+}
+
+impl From<OnChainStatus> for String {
+    fn from(s: OnChainStatus) -> Self {
+        s.to_string()
+    }
+}
+
+impl Display for OnChainStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OnChainStatus::Waiting => write!(f, "waiting"),
+            OnChainStatus::Pending => write!(f, "pending"),
+            OnChainStatus::Success => write!(f, "success"),
+            OnChainStatus::Failed => write!(f, "failed"),
+            OnChainStatus::Unknown(s) => write!(f, "{}", s),
+        }
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ItemMetaRes {
@@ -81,6 +320,81 @@ pub struct Tag {
     pub value: String,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeedingStatus {
+    // not yet part of a bundle known to arweave
+    Pending,
+    // bundled and posted to arweave, waiting to be mined
+    Seeded,
+    Confirmed { confirmations: u32 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GatewayHealth {
+    pub url: Url,
+    pub healthy: bool,
+    // round trip of the health check, None when it failed
+    pub latency: Option<Duration>,
+}
+
+// outcome of `ASClient::self_test`, with the time taken by each stage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfTestReport {
+    pub item_id: String,
+    pub upload: Duration,
+    pub payment: Duration,
+    // until the gateway lists the order as paid
+    pub confirmation: Duration,
+    pub download: Duration,
+}
+
+impl SelfTestReport {
+    pub fn total(&self) -> Duration {
+        self.upload + self.payment + self.confirmation + self.download
+    }
+}
+
+// paid items of the client's signer not yet seeded, see `ASClient::bundler_backlog`
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct BacklogInfo {
+    pub pending_items: usize,
+    // blocks until the last of them is expected to be seeded
+    pub blocks_to_seed: u64,
+    pub estimated_time_to_seed: Duration,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphQLTxRes {
+    pub data: GraphQLTxData,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphQLTxData {
+    pub transaction: Option<GraphQLTx>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphQLTx {
+    pub bundled_in: Option<GraphQLBundle>,
+    pub block: Option<GraphQLBlock>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphQLBundle {
+    pub id: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphQLBlock {
+    pub height: u64,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkInfoRes {
+    pub height: u64,
+}
+
 #[derive(Debug)]
 pub enum ASError {
     ArgumentError { arg: String },
@@ -89,7 +403,55 @@ pub enum ASError {
     ReqwestError(reqwest::Error),
     IOError(std::io::Error),
     APIError { e: String },
+    Unauthorized { e: String },
+    ResponseTooLarge { max: usize },
+    ItemTooLarge { size: u64, max: u64 },
+    DataTooLarge { size: usize, max: usize },
+    NoKeypair { e: String },
+    BundlerMismatch {
+        advertised: String,
+        submission: String,
+    },
+    FeeExceedsLimit {
+        currency: String,
+        quoted: Decimal,
+        limit: Decimal,
+    },
+    // a fee override under the fee the gateway quoted, in base units
+    FeeTooLow {
+        currency: String,
+        quoted: u64,
+        fee: u64,
+    },
+    Timeout { e: String },
+    // the host didn't resolve or refused the connection
+    ConnectionFailed { url: String, reason: String },
+    // a failed request with the bodies exchanged, see
+    // `ASClientBuilder::capture_debug_bodies`
+    WithBodies {
+        e: Box<ASError>,
+        request: Option<String>,
+        response: String,
+    },
+    JSONError(serde_json::Error),
     ArLoaderError(arloader::error::Error),
+    DecimalMismatch {
+        currency: String,
+        item_decimals: i64,
+        token_decimals: i64,
+    },
+    // the order and the fee endpoint disagree on the currency's decimals
+    FeeDecimalMismatch {
+        currency: String,
+        item_decimals: i64,
+        fee_decimals: i64,
+    },
+    PrecisionLoss {
+        symbol: String,
+        value: String,
+    },
+    // work started after `ASClient::shutdown`
+    ShutDown,
     // RingError(Unspecified),
 }
 
@@ -100,25 +462,236 @@ impl Display for ASError {
             ASError::TokenError { arg } => write!(f, "token not valid: {}", arg),
             ASError::URLError { url } => write!(f, "invalid url: {}", url),
             ASError::APIError { e } => write!(f, "api: {}", e),
+            ASError::Unauthorized { e } => write!(f, "unauthorized: {}", e),
+            ASError::ResponseTooLarge { max } => {
+                write!(f, "response is larger than the {} bytes limit", max)
+            }
+            ASError::ItemTooLarge { size, max } => write!(
+                f,
+                "item is {} bytes, over the {} bytes limit",
+                size, max
+            ),
+            ASError::DataTooLarge { size, max } => write!(
+                f,
+                "everpay tx data is {} bytes, the limit is {} bytes",
+                size, max
+            ),
+            ASError::NoKeypair { e } => write!(
+                f,
+                "no arweave keypair to sign with ({}), load a keyfile with `ASClient::from_keypair_bytes` or `Arweave::from_keypair_path`",
+                e
+            ),
+            ASError::BundlerMismatch {
+                advertised,
+                submission,
+            } => write!(
+                f,
+                "submission asks to pay {}, the gateway advertises bundler {}",
+                submission, advertised
+            ),
+            ASError::FeeExceedsLimit {
+                currency,
+                quoted,
+                limit,
+            } => write!(
+                f,
+                "fee of {} {} is over the {} {} limit",
+                quoted, currency, limit, currency
+            ),
+            ASError::FeeTooLow {
+                currency,
+                quoted,
+                fee,
+            } => write!(
+                f,
+                "fee of {} is under the {} quoted by the gateway ({} base units)",
+                fee, quoted, currency
+            ),
+            ASError::Timeout { e } => write!(f, "timed out: {}", e),
+            ASError::ConnectionFailed { url, reason } => {
+                write!(f, "could not connect to {}: {}", url, reason)
+            }
+            ASError::WithBodies {
+                e,
+                request,
+                response,
+            } => {
+                write!(f, "{}", e)?;
+                if let Some(request) = request {
+                    write!(f, "\nrequest body: {}", request)?;
+                }
+                write!(f, "\nresponse body: {}", response)
+            }
+            ASError::JSONError(e) => write!(f, "json: {}", e),
             ASError::ReqwestError(e) => write!(f, "reqwest: {}", e),
             ASError::IOError(e) => write!(f, "io: {}", e),
-            ASError::ArLoaderError( e ) => write!(f, "arloader: {}", e)
+            ASError::ArLoaderError( e ) => write!(f, "arloader: {}", e),
+            ASError::DecimalMismatch {
+                currency,
+                item_decimals,
+                token_decimals,
+            } => write!(
+                f,
+                "decimals mismatch for {}: item quoted with {} decimals, token has {}",
+                currency, item_decimals, token_decimals
+            ),
+            ASError::FeeDecimalMismatch {
+                currency,
+                item_decimals,
+                fee_decimals,
+            } => write!(
+                f,
+                "decimals mismatch for {}: item quoted with {} decimals, fee endpoint with {}",
+                currency, item_decimals, fee_decimals
+            ),
+            ASError::PrecisionLoss { symbol, value } => write!(
+                f,
+                "{} {} can't be represented exactly in base units",
+                value, symbol
+            ),
+            ASError::ShutDown => write!(f, "the client is shut down"),
             // ASError::ParseIntError(e) => write!(f, "parse int error: {}", e),
             // ASError::RingError(e) => write!(f, "ring error: {}", e),
         }
     }
 }
 
+// timeouts and connection failures get their own variant so they can be
+// matched, and shown to users, without going through `reqwest::Error`
 impl From<reqwest::Error> for ASError {
     fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            return ASError::Timeout { e: e.to_string() };
+        }
+        if e.is_connect() {
+            return ASError::ConnectionFailed {
+                url: e.url().map(|u| u.to_string()).unwrap_or_default(),
+                reason: connect_failure_reason(&e),
+            };
+        }
         ASError::ReqwestError(e)
     }
 }
 
+// Readable cause of a connect error. reqwest nests it a few levels down, as
+// an io error or a hyper dns error.
+fn connect_failure_reason(e: &reqwest::Error) -> String {
+    let mut source = std::error::Error::source(e);
+    let mut innermost = None;
+    while let Some(s) = source {
+        if let Some(io) = s.downcast_ref::<std::io::Error>() {
+            match io.kind() {
+                std::io::ErrorKind::ConnectionRefused => {
+                    return String::from("the connection was refused")
+                }
+                std::io::ErrorKind::ConnectionReset => {
+                    return String::from("the connection was reset")
+                }
+                std::io::ErrorKind::ConnectionAborted => {
+                    return String::from("the connection was aborted")
+                }
+                _ => {}
+            }
+        }
+        let msg = s.to_string();
+        if msg.contains("dns error") || msg.contains("failed to lookup address") {
+            return String::from("the host name could not be resolved");
+        }
+        innermost = Some(msg);
+        source = s.source();
+    }
+    innermost.unwrap_or_else(|| String::from("the connection failed"))
+}
+
+impl From<std::io::Error> for ASError {
+    fn from(e: std::io::Error) -> Self {
+        ASError::IOError(e)
+    }
+}
+
+impl From<serde_json::Error> for ASError {
+    fn from(e: serde_json::Error) -> Self {
+        ASError::JSONError(e)
+    }
+}
+
 impl ASError {
     pub fn api_error(e: &str) -> ASError {
         ASError::APIError { e: e.to_string() }
     }
+
+    // the error itself, or the one `WithBodies` wraps: match on this to handle
+    // errors the same way whether bodies are captured or not
+    pub fn root(&self) -> &ASError {
+        match self {
+            ASError::WithBodies { e, .. } => e.root(),
+            e => e,
+        }
+    }
+
+    // converts a non 200 response to an error
+    pub(crate) async fn from_response(res: Response) -> ASError {
+        let status = res.status();
+        let body = match res.bytes().await {
+            Ok(b) => b,
+            Err(e) => return ASError::from(e),
+        };
+        ASError::from_status_body(status, &body)
+    }
+
+    pub(crate) fn from_status_body(status: StatusCode, body: &[u8]) -> ASError {
+        let mut e = api_error_message(body);
+        if e.is_empty() {
+            e = status.to_string();
+        }
+        ASError::APIError { e }
+    }
+
+    // an `APIError` as `Unauthorized`, inside `WithBodies` if it's wrapped
+    pub(crate) fn into_unauthorized(self) -> ASError {
+        match self {
+            ASError::WithBodies {
+                e,
+                request,
+                response,
+            } => ASError::WithBodies {
+                e: Box::new(e.into_unauthorized()),
+                request,
+                response,
+            },
+            ASError::APIError { e } => ASError::Unauthorized { e },
+            e => e,
+        }
+    }
+}
+
+// error message of a failed request: `{"error": ...}`, `{"message": ...}`, a
+// json string or, failing that, the raw body
+fn api_error_message(body: &[u8]) -> String {
+    if let Ok(r) = serde_json::from_slice::<APIErrorRes>(body) {
+        return r.error;
+    }
+    if let Ok(e) = serde_json::from_slice::<String>(body) {
+        return e;
+    }
+    String::from_utf8_lossy(body).trim().to_string()
+}
+
+// parses a base url, making sure it ends with a `/` as endpoints are appended to it
+pub(crate) fn parse_url(url: &str) -> Result<Url, ASError> {
+    let mut u = Url::parse(url).map_err(|_| ASError::URLError {
+        url: url.to_string(),
+    })?;
+    if u.cannot_be_a_base() {
+        return Err(ASError::URLError {
+            url: url.to_string(),
+        });
+    }
+    if !u.path().ends_with('/') {
+        let path = format!("{}/", u.path());
+        u.set_path(&path);
+    }
+    Ok(u)
 }
 
 impl From<arloader::error::Error> for ASError {
@@ -135,10 +708,47 @@ where
     D: Deserializer<'de>,
 {
     let s = String::deserialize(deserializer)?;
+    // RFC 3339 covers any fraction of a second and offsets other than `Z`
+    if let Ok(d) = DateTime::parse_from_rfc3339(&s) {
+        return Ok(d.with_timezone(&Utc));
+    }
     Utc.datetime_from_str(&s, FORMAT)
         .map_err(serde::de::Error::custom)
 }
 
+// amounts are sent as strings, some versions send them as json numbers. Only
+// integers are taken as numbers: serde_json reads larger ones and fractions as
+// f64, which would round the amount.
+pub(crate) fn string_or_number<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrNumber {
+        String(String),
+        U64(u64),
+        I64(i64),
+    }
+
+    Ok(match StringOrNumber::deserialize(deserializer)? {
+        StringOrNumber::String(s) => s,
+        StringOrNumber::U64(n) => n.to_string(),
+        StringOrNumber::I64(n) => n.to_string(),
+    })
+}
+
+fn option_string_or_number<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Wrapper(#[serde(deserialize_with = "string_or_number")] String);
+
+    let v = Option::deserialize(deserializer)?;
+    Ok(v.map(|Wrapper(a)| a))
+}
+
 fn option_datefmt<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
 where
     D: Deserializer<'de>,
@@ -149,3 +759,179 @@ where
     let v = Option::deserialize(deserializer)?;
     Ok(v.map(|Wrapper(a)| a))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_builds_data_urls() {
+        let res = SubmitNativeRes {
+            item_id: "BewjUEppPQ9pljVrjMxF7A2Kkz5ZJt_Q7tXRkQDm2VQ".to_string(),
+        };
+
+        assert_eq!(
+            res.data_url(&Url::parse("https://arseed.web3infra.dev").unwrap())
+                .as_str(),
+            "https://arseed.web3infra.dev/BewjUEppPQ9pljVrjMxF7A2Kkz5ZJt_Q7tXRkQDm2VQ"
+        );
+        assert_eq!(
+            res.data_url(&Url::parse("https://example.com/arseed/").unwrap())
+                .as_str(),
+            "https://example.com/arseed/BewjUEppPQ9pljVrjMxF7A2Kkz5ZJt_Q7tXRkQDm2VQ"
+        );
+    }
+
+    #[test]
+    fn it_displays_orders() {
+        let order = OrderRes {
+            item_id: String::from("BewjUEppPQ9pljVrjMxF7A2Kkz5ZJt_Q7tXRkQDm2VQ"),
+            currency: String::from("USDC"),
+            decimals: 6,
+            fee: String::from("1234"),
+            size: 1229,
+            expected_block: 1000,
+            payment_status: String::from("paid"),
+            on_chain_status: String::from("success"),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            order.to_string(),
+            "BewjUEppPQ9pljVrjMxF7A2Kkz5ZJt_Q7tXRkQDm2VQ (usdc, paid, success, 1.2KB)"
+        );
+        assert_eq!(
+            format!("{:#}", order),
+            "BewjUEppPQ9pljVrjMxF7A2Kkz5ZJt_Q7tXRkQDm2VQ (usdc, paid, success, 1.2KB) fee 0.001234 usdc, expected block 1000"
+        );
+
+        assert_eq!(human_size(512), "512B");
+        assert_eq!(human_size(3 * 1024 * 1024), "3.0MB");
+    }
+
+    #[test]
+    fn it_reads_order_statuses() {
+        let order = OrderRes {
+            payment_status: "paid".to_string(),
+            on_chain_status: "waiting".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(order.payment_status(), PaymentStatus::Paid);
+        assert_eq!(order.on_chain_status(), OnChainStatus::Waiting);
+
+        let s: PaymentStatus = serde_json::from_str(r#""expired""#).unwrap();
+        assert_eq!(s, PaymentStatus::Expired);
+        let s: OnChainStatus = serde_json::from_str(r#""reorged""#).unwrap();
+        assert_eq!(s, OnChainStatus::Unknown("reorged".to_string()));
+        assert_eq!(serde_json::to_string(&s).unwrap(), r#""reorged""#);
+        assert_eq!(
+            serde_json::to_string(&OnChainStatus::Success).unwrap(),
+            r#""success""#
+        );
+    }
+
+    #[test]
+    fn it_reads_amounts_as_string_or_number() {
+        let fee: FeeRes =
+            serde_json::from_str(r#"{"currency":"AR","decimals":12,"finalFee":"1000"}"#).unwrap();
+        assert_eq!(fee.final_fee, "1000");
+        let fee: FeeRes =
+            serde_json::from_str(r#"{"currency":"AR","decimals":12,"finalFee":1000}"#).unwrap();
+        assert_eq!(fee.final_fee, "1000");
+        let fee: FeeRes =
+            serde_json::from_str(r#"{"currency":"AR","decimals":12,"finalFee":-1}"#).unwrap();
+        assert_eq!(fee.final_fee, "-1");
+
+        // fractions and integers over u64::MAX would be rounded
+        for fee in ["0.5", "18446744073709551616", "null"] {
+            assert!(serde_json::from_str::<FeeRes>(&format!(
+                r#"{{"currency":"AR","decimals":12,"finalFee":{}}}"#,
+                fee
+            ))
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn it_reads_order_dates() {
+        let created_at = |date: &str| {
+            let order: OrderRes = serde_json::from_value(serde_json::json!({
+                "id": 1,
+                "createdAt": date,
+                "itemId": "", "signer": "", "signType": 1, "size": 0,
+                "currency": "", "decimals": 0, "fee": "0",
+                "paymentExpiredTime": 0, "expectedBlock": 0,
+                "paymentStatus": "", "paymentId": "", "onChainStatus": "",
+            }))
+            .unwrap();
+            order.created_at.unwrap().to_rfc3339()
+        };
+
+        assert_eq!(created_at("2022-06-24T03:29:54.174Z"), "2022-06-24T03:29:54.174+00:00");
+        assert_eq!(created_at("2022-06-24T03:29:54Z"), "2022-06-24T03:29:54+00:00");
+        assert_eq!(created_at("2022-06-24T03:29:54.174+00:00"), "2022-06-24T03:29:54.174+00:00");
+        assert_eq!(created_at("2022-06-24T05:29:54+02:00"), "2022-06-24T03:29:54+00:00");
+        assert_eq!(
+            created_at("2022-06-24T03:29:54.174123456Z"),
+            "2022-06-24T03:29:54.174123456+00:00"
+        );
+    }
+
+    #[test]
+    fn it_reads_fee_breakdown() {
+        let res: ItemSubmissionRes = serde_json::from_str(
+            r#"{"itemId":"a","bundler":"b","currency":"AR","decimals":12,"fee":"30",
+                "paymentExpiredTime":0,"expectedBlock":0}"#,
+        )
+        .unwrap();
+        assert_eq!(res.base_fee, None);
+        assert_eq!(res.size_fee, None);
+        assert_eq!(res.service_fee, None);
+
+        let res: ItemSubmissionRes = serde_json::from_str(
+            r#"{"itemId":"a","bundler":"b","currency":"AR","decimals":12,"fee":"30",
+                "paymentExpiredTime":0,"expectedBlock":0,
+                "baseFee":"10","sizeFee":15,"serviceFee":null}"#,
+        )
+        .unwrap();
+        assert_eq!(res.base_fee.as_deref(), Some("10"));
+        assert_eq!(res.size_fee.as_deref(), Some("15"));
+        assert_eq!(res.service_fee, None);
+    }
+
+    #[test]
+    fn it_computes_payment_time_remaining() {
+        let res = ItemSubmissionRes {
+            payment_expired_time: 1_700_000_270,
+            ..Default::default()
+        };
+        let at = |secs: u64| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+
+        assert_eq!(
+            res.time_remaining_at(at(1_700_000_000)),
+            Some(Duration::from_secs(270))
+        );
+        assert_eq!(res.time_remaining_at(at(1_700_000_270)), None);
+        assert_eq!(res.time_remaining_at(at(1_700_000_300)), None);
+        assert_eq!(
+            res.payment_deadline().unwrap().to_rfc3339(),
+            "2023-11-14T22:17:50+00:00"
+        );
+
+        let expired = ItemSubmissionRes {
+            payment_expired_time: -1,
+            ..Default::default()
+        };
+        assert!(expired.is_expired());
+    }
+
+    #[test]
+    fn it_reads_api_error_messages() {
+        assert_eq!(api_error_message(br#"{"error":"not found"}"#), "not found");
+        assert_eq!(api_error_message(br#"{"message":"not found"}"#), "not found");
+        assert_eq!(api_error_message(br#""not found""#), "not found");
+        assert_eq!(api_error_message(b"not found\n"), "not found");
+        assert_eq!(api_error_message(b"bad \xff gateway"), "bad \u{fffd} gateway");
+        assert_eq!(api_error_message(b""), "");
+    }
+}
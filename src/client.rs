@@ -2,78 +2,928 @@ use std::collections::HashMap;
 
 use crate::{
     arseeding_types::{
-        APIErrorRes, ASError, BundlerRes, FeeRes, ItemMetaRes, ItemSubmissionRes, OrderRes,
-        SubmitNativeRes,
+        parse_url, ASError, BacklogInfo, BundlerRes, FeeRes, GatewayHealth, GraphQLTx,
+        GraphQLTxRes, ItemMetaRes, ItemSubmissionRes, NetworkInfoRes, OnChainStatus, OrderRes,
+        PaymentStatus, PendingOrder, SeedingStatus, SelfTestReport, SortOrder, SubmitNativeRes,
     },
-    everpay::Everpay,
-    everpay_types::PayTxData,
+    data_item,
+    everpay::{ArweaveSigner, Everpay},
+    everpay_client::EverpayClient,
+    everpay_types::{PayTxData, Signer, TokenList, DEFAULT_EVERPAY_URL},
+    tags,
+    util::{from_base_units, poll_until, Backoff},
 };
 use arloader::{
-    transaction::{FromUtf8Strs, Tag},
+    crypto::Provider,
+    transaction::{Base64, FromUtf8Strs, Tag},
     Arweave,
 };
-use reqwest::{Client, StatusCode};
+use bytes::Bytes;
+use futures::{future::try_join_all, stream, Stream, StreamExt, TryStreamExt};
+use jsonwebkey::JsonWebKey;
+use reqwest::{multipart, Body, Certificate, Client, Proxy, Response, StatusCode};
+use ring::{
+    digest::{Context, SHA256},
+    rand::SystemRandom,
+    signature::RsaKeyPair,
+};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use serde_json::json;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::{
+    io::{AsyncWrite, AsyncWriteExt},
+    sync::Notify,
+};
 
 use url::Url;
+
+// Uploads and payments run in the futures returned by the client, nothing is
+// spawned. Dropping one of them (e.g. a task cut short by the runtime shutting
+// down when `main` returns) abandons its work, call `shutdown` first to let
+// in-flight uploads and payments finish.
 pub struct ASClient {
     client: Client,
-    arweave: Arweave,
+    // None for read only clients, see `ASClient::read_only`
+    arweave: Option<Arweave>,
+    arweave_url: Url,
     url: Url,
-    everpay: Everpay,
+    everpay: Option<Everpay>,
+    idempotency: Option<IdempotencyCache>,
+    default_tags: HashMap<String, String>,
+    unix_time_tag: bool,
+    content_sha256_tag: bool,
+    capture_debug_bodies: bool,
+    max_response_size: Option<usize>,
+    gateways: Vec<Url>,
+    gateway_health: Arc<Mutex<Vec<GatewayHealth>>>,
+    default_currency: Option<String>,
+    content_digest: bool,
+    api_key: Option<String>,
+    // largest item submitted, set on the builder
+    max_item_size: Option<u64>,
+    // uploads and payments in progress, `idle` is notified when it drops to 0
+    in_flight: AtomicUsize,
+    idle: Notify,
+    // set by `shutdown`, new uploads and payments are refused from then on
+    shut_down: AtomicBool,
+    // most a single payment may cost, by lowercase currency
+    max_fees: HashMap<String, Decimal>,
+    // extra attempts `bundle_and_submit` makes after a transient failure
+    submit_retries: u32,
+    // signs items instead of `arweave` when set
+    item_signer: Option<Arc<dyn Signer + Send + Sync>>,
+    random_anchor: bool,
+}
+
+// counts as in-flight work from creation to drop
+struct InFlight<'a> {
+    client: &'a ASClient,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        if self.client.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.client.idle.notify_waiters();
+        }
+    }
 }
 
 pub const DEFAULT_ARSEEDING_URL: &str = "https://arseed.web3infra.dev";
 
+// arseeding testnet gateway, paid with everpay testnet (`TESTNET_EVERPAY_URL`)
+pub const TESTNET_ARSEEDING_URL: &str = "https://arseed-dev.web3infra.dev";
+
+pub const DEFAULT_ARWEAVE_URL: &str = "https://arweave.net";
+
+const UPLOAD_CHUNK_SIZE: usize = 64 * 1024;
+
+// items submitted at the same time by `submit_items_stream`
+const SUBMIT_CONCURRENCY: usize = 4;
+
+// items uploaded, then paid for, at a time by `submit_many_and_pay`: if a
+// submission fails at most this many orders are left unpaid
+const SUBMIT_BATCH_LEN: usize = 500;
+
+// items downloaded at the same time by `get_data_many`
+const DOWNLOAD_CONCURRENCY: usize = 8;
+
+// a gateway taking longer than this to answer its health check is unhealthy
+const GATEWAY_HEALTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+// average arweave block time
+const ARWEAVE_BLOCK_TIME: Duration = Duration::from_secs(120);
+
+// delays between the attempts of `bundle_and_submit`
+const SUBMIT_BACKOFF: Backoff = Backoff {
+    initial: Duration::from_secs(1),
+    max: Duration::from_secs(10),
+    factor: 2,
+};
+
+// time given to the gateway to see the payment in `self_test`
+const SELF_TEST_PAYMENT_TIMEOUT: Duration = Duration::from_secs(120);
+
+// bytes of each body kept in `ASError::WithBodies`
+pub const DEBUG_BODY_LIMIT: usize = 4096;
+
+// rescales a base unit amount from one decimals scale to another, None if the
+// conversion overflows or would lose precision
+pub fn convert_decimals(amount: u64, from: i64, to: i64) -> Option<u64> {
+    let diff = u32::try_from((to - from).abs()).ok()?;
+    let factor = 10u64.checked_pow(diff)?;
+
+    if to >= from {
+        amount.checked_mul(factor)
+    } else if amount % factor == 0 {
+        Some(amount / factor)
+    } else {
+        None
+    }
+}
+
+// total cost of an upload in the token's base units: the bundle fee quoted by
+// the gateway plus everpay's transfer fee for paying it
+pub fn upload_cost(fee: &FeeRes, token: &TokenList) -> Result<u64, ASError> {
+    let bundle_fee: u64 = fee.final_fee.parse().map_err(|_| ASError::ArgumentError {
+        arg: format!("invalid fee: {}", fee.final_fee),
+    })?;
+    let bundle_fee = convert_decimals(bundle_fee, fee.decimals, token.decimals).ok_or(
+        ASError::DecimalMismatch {
+            currency: fee.currency.clone(),
+            item_decimals: fee.decimals,
+            token_decimals: token.decimals,
+        },
+    )?;
+    let transfer_fee: u64 = token
+        .transfer_fee
+        .parse()
+        .map_err(|_| ASError::ArgumentError {
+            arg: format!("invalid transfer fee: {}", token.transfer_fee),
+        })?;
+
+    bundle_fee
+        .checked_add(transfer_fee)
+        .ok_or(ASError::ArgumentError {
+            arg: String::from("upload cost overflows"),
+        })
+}
+
+// healthy gateways first, fastest first
+fn rank_gateways(mut gateways: Vec<GatewayHealth>) -> Vec<GatewayHealth> {
+    gateways.sort_by_key(|g| (!g.healthy, g.latency));
+    gateways
+}
+
+// fails if `total` (in the token's base units) is more than `limit` (in human
+// readable units)
+pub fn check_fee_limit(
+    currency: &str,
+    total: u128,
+    decimals: i64,
+    limit: Decimal,
+) -> Result<(), ASError> {
+    let quoted = from_base_units(&total.to_string(), decimals)?;
+    if quoted > limit {
+        return Err(ASError::FeeExceedsLimit {
+            currency: currency.to_string(),
+            quoted,
+            limit,
+        });
+    }
+    Ok(())
+}
+
+// `order` with `fee` as its fee, which can't be under the quoted one. Both
+// are in the order's base units.
+fn with_fee_override(order: &ItemSubmissionRes, fee: u64) -> Result<ItemSubmissionRes, ASError> {
+    let quoted: u64 = order.fee.parse().map_err(|_| ASError::ArgumentError {
+        arg: format!("invalid fee: {}", order.fee),
+    })?;
+    if fee < quoted {
+        return Err(ASError::FeeTooLow {
+            currency: order.currency.clone(),
+            quoted,
+            fee,
+        });
+    }
+
+    Ok(ItemSubmissionRes {
+        fee: fee.to_string(),
+        ..order.clone()
+    })
+}
+
+// The payment address comes from the submission response alone, a tampered
+// response would have the fee sent elsewhere. It must be the bundler the
+// gateway advertises on its own endpoint.
+pub fn verify_gateway_everpay_match(
+    advertised: &BundlerRes,
+    submission: &ItemSubmissionRes,
+) -> Result<(), ASError> {
+    // ethereum addresses may differ in checksum case only
+    if !advertised.bundler.eq_ignore_ascii_case(&submission.bundler) {
+        return Err(ASError::BundlerMismatch {
+            advertised: advertised.bundler.clone(),
+            submission: submission.bundler.clone(),
+        });
+    }
+    Ok(())
+}
+
+// a signed item on its way to `bundle/tx`, see `submit_item_body`
+struct ItemBody {
+    body: Body,
+    len: u64,
+    // `Content-Digest` header value, if enabled
+    digest: Option<String>,
+    // kept for `capture_debug_bodies`
+    request: Option<Bytes>,
+}
+
+impl ItemBody {
+    fn from_bytes(item: Bytes, content_digest_enabled: bool) -> Self {
+        ItemBody {
+            body: Body::from(item.clone()),
+            len: item.len() as u64,
+            digest: content_digest_enabled.then(|| content_digest(&item)),
+            request: Some(item),
+        }
+    }
+}
+
+// failures worth resubmitting the same item for: the gateway couldn't be
+// reached or didn't answer in time
+fn is_transient(e: &ASError) -> bool {
+    match e.root() {
+        ASError::ReqwestError(e) => e.is_connect(),
+        ASError::ConnectionFailed { .. } | ASError::Timeout { .. } => true,
+        _ => false,
+    }
+}
+
+// The fee endpoint and the order both give the currency's decimals, from two
+// different endpoints. Paying with the wrong ones would mispay by orders of
+// magnitude so any disagreement is an error.
+pub fn reconcile_decimals(fee: &FeeRes, submission: &ItemSubmissionRes) -> Result<(), ASError> {
+    if !fee.currency.eq_ignore_ascii_case(&submission.currency) {
+        return Err(ASError::ArgumentError {
+            arg: format!(
+                "fee quoted in {} but the order is in {}",
+                fee.currency, submission.currency
+            ),
+        });
+    }
+    if fee.decimals != submission.decimals {
+        return Err(ASError::FeeDecimalMismatch {
+            currency: submission.currency.clone(),
+            item_decimals: submission.decimals,
+            fee_decimals: fee.decimals,
+        });
+    }
+    Ok(())
+}
+
+// splits `orders` into runs whose payment data (`PayTxData` as json) is at
+// most `max` bytes long. An order too large on its own gets a run of its own.
+fn payment_batches(orders: &[ItemSubmissionRes], max: usize) -> Vec<&[ItemSubmissionRes]> {
+    let empty = serde_json::to_string(&PayTxData {
+        app_name: String::from("arseeding"),
+        action: String::from("payment"),
+        item_ids: vec![],
+    })
+    .unwrap()
+    .len();
+
+    let mut batches = vec![];
+    let mut start = 0;
+    let mut len = empty;
+    for (i, order) in orders.iter().enumerate() {
+        let id_len = serde_json::to_string(&order.item_id).unwrap().len();
+        // ids after the first one are preceded by a comma
+        if i > start && len + 1 + id_len > max {
+            batches.push(&orders[start..i]);
+            start = i;
+            len = empty;
+        }
+        len += id_len + if i > start { 1 } else { 0 };
+    }
+    if start < orders.len() {
+        batches.push(&orders[start..]);
+    }
+
+    batches
+}
+
+// splits items of `sizes` bytes into runs of at most `max_len` items and
+// `max_bytes` bytes, returned as run lengths. An item over `max_bytes` gets a
+// run of its own.
+fn submit_batches(sizes: &[u64], max_bytes: Option<u64>, max_len: usize) -> Vec<usize> {
+    let max_bytes = max_bytes.unwrap_or(u64::MAX);
+    let mut batches = vec![];
+    let mut len = 0;
+    let mut bytes = 0u64;
+    for size in sizes {
+        if len > 0 && (len == max_len || bytes.saturating_add(*size) > max_bytes) {
+            batches.push(len);
+            len = 0;
+            bytes = 0;
+        }
+        len += 1;
+        bytes = bytes.saturating_add(*size);
+    }
+    if len > 0 {
+        batches.push(len);
+    }
+
+    batches
+}
+
+fn backlog(orders: &[OrderRes], height: u64) -> BacklogInfo {
+    let pending: Vec<&OrderRes> = orders
+        .iter()
+        .filter(|o| o.payment_status() == PaymentStatus::Paid)
+        .filter(|o| {
+            matches!(
+                o.on_chain_status(),
+                OnChainStatus::Waiting | OnChainStatus::Pending
+            )
+        })
+        .collect();
+    let blocks_to_seed = pending
+        .iter()
+        .map(|o| {
+            u64::try_from(o.expected_block)
+                .unwrap_or(0)
+                .saturating_sub(height)
+        })
+        .max()
+        .unwrap_or(0);
+
+    BacklogInfo {
+        pending_items: pending.len(),
+        blocks_to_seed,
+        estimated_time_to_seed: ARWEAVE_BLOCK_TIME
+            * u32::try_from(blocks_to_seed).unwrap_or(u32::MAX),
+    }
+}
+
+// `Content-Digest` header value (RFC 9530) of an upload body
+pub fn content_digest(data: &[u8]) -> String {
+    let mut ctx = Context::new(&SHA256);
+    ctx.update(data);
+    format!("sha-256=:{}:", base64::encode(ctx.finish()))
+}
+
+// Key identifying an upload by its content: the currency, the tags (sorted) and
+// the data. The item id can't be used as RSA-PSS signatures are salted, signing
+// the same bytes twice gives two different item ids.
+pub fn idempotency_key(data: &[u8], tags: &HashMap<String, String>, currency: &str) -> String {
+    let mut sorted: Vec<(&String, &String)> = tags.iter().collect();
+    sorted.sort();
+
+    let mut ctx = Context::new(&SHA256);
+    ctx.update(currency.to_lowercase().as_bytes());
+    ctx.update(b"\0");
+    for (k, v) in sorted {
+        // length prefixed so that tag boundaries can't be shifted
+        for part in [k.as_bytes(), v.as_bytes()] {
+            ctx.update(&(part.len() as u64).to_le_bytes());
+            ctx.update(part);
+        }
+    }
+    ctx.update(data);
+
+    Base64(ctx.finish().as_ref().to_vec()).to_string()
+}
+
+// In process cache of paid uploads (idempotency key -> item id) used by
+// `send_and_pay` to avoid paying twice for the same content when a call is
+// retried. The order is recorded as pending before it's paid, so a retry after
+// a transfer that failed on the client side but went through checks the
+// order's `payment_status` instead of paying again. It only lives as long as
+// the process: to keep it across restarts, persist `entries()` and
+// `pending_entries()` (e.g. as json) and load them back with `from_entries`
+// and `with_pending_entries`. Concurrent calls with the same content are not
+// deduplicated.
+#[derive(Clone, Default)]
+pub struct IdempotencyCache {
+    entries: Arc<Mutex<HashMap<String, String>>>,
+    pending: Arc<Mutex<HashMap<String, PendingOrder>>>,
+}
+
+impl IdempotencyCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_entries(entries: HashMap<String, String>) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(entries)),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_pending_entries(self, pending: HashMap<String, PendingOrder>) -> Self {
+        *self.pending.lock().unwrap() = pending;
+        self
+    }
+
+    pub fn entries(&self) -> HashMap<String, String> {
+        self.entries.lock().unwrap().clone()
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.entries.lock().unwrap().get(key).cloned()
+    }
+
+    pub fn insert(&self, key: String, item_id: String) {
+        self.pending.lock().unwrap().remove(&key);
+        self.entries.lock().unwrap().insert(key, item_id);
+    }
+
+    pub fn pending_entries(&self) -> HashMap<String, PendingOrder> {
+        self.pending.lock().unwrap().clone()
+    }
+
+    // order submitted for `key` whose payment may or may not have gone through
+    pub fn pending(&self, key: &str) -> Option<PendingOrder> {
+        self.pending.lock().unwrap().get(key).cloned()
+    }
+
+    pub fn insert_pending(&self, key: String, order: PendingOrder) {
+        self.pending.lock().unwrap().insert(key, order);
+    }
+
+    pub fn remove_pending(&self, key: &str) {
+        self.pending.lock().unwrap().remove(key);
+    }
+}
+
+// builds an `Arweave` from an in memory JWK (e.g. read from an env var or a secret manager)
+pub fn arweave_from_keypair_bytes(jwk: &[u8], base_url: Url) -> Result<Arweave, ASError> {
+    let jwk = std::str::from_utf8(jwk)
+        .ok()
+        .and_then(|s| s.parse::<JsonWebKey>().ok())
+        .ok_or(ASError::ArgumentError {
+            arg: String::from("keypair is not a valid JWK"),
+        })?;
+
+    let keypair =
+        RsaKeyPair::from_pkcs8(&jwk.key.as_ref().to_der()).map_err(|e| ASError::ArgumentError {
+            arg: format!("keypair is not a valid RSA key: {}", e),
+        })?;
+
+    Ok(Arweave {
+        name: String::from("arweave"),
+        units: String::from("winstons"),
+        base_url,
+        crypto: Provider {
+            keypair,
+            sr: SystemRandom::new(),
+        },
+    })
+}
+
 impl ASClient {
     pub fn new(url: Url, client: Client, arweave: Arweave, everpay: Everpay) -> Self {
+        let mut c = Self::read_only(url, client);
+        c.arweave_url = arweave.base_url.clone();
+        c.arweave = Some(arweave);
+        c.everpay = Some(everpay);
+        c
+    }
+
+    // Client without keys for the read endpoints (item meta, data, fees, orders,
+    // seeding status). Signing items fails with `NoKeypair` and paying with
+    // `ArgumentError`.
+    pub fn read_only(url: Url, client: Client) -> Self {
         ASClient {
             url,
             client,
-            arweave,
+            arweave: None,
+            arweave_url: Url::parse(DEFAULT_ARWEAVE_URL).unwrap(),
+            everpay: None,
+            idempotency: None,
+            default_tags: HashMap::new(),
+            unix_time_tag: false,
+            content_sha256_tag: false,
+            capture_debug_bodies: false,
+            max_response_size: None,
+            gateways: vec![],
+            gateway_health: Arc::new(Mutex::new(vec![])),
+            default_currency: None,
+            content_digest: false,
+            api_key: None,
+            max_item_size: None,
+            in_flight: AtomicUsize::new(0),
+            idle: Notify::new(),
+            shut_down: AtomicBool::new(false),
+            max_fees: HashMap::new(),
+            submit_retries: 0,
+            item_signer: None,
+            random_anchor: false,
+        }
+    }
+
+    pub fn from_url_str(
+        url: &str,
+        client: Client,
+        arweave: Arweave,
+        everpay: Everpay,
+    ) -> Result<Self, ASError> {
+        Ok(Self::new(parse_url(url)?, client, arweave, everpay))
+    }
+
+    pub async fn from_keypair_bytes(
+        jwk: &[u8],
+        gateway: &str,
+        everpay_url: &str,
+    ) -> Result<Self, ASError> {
+        let arweave_url = parse_url(DEFAULT_ARWEAVE_URL)?;
+        let signer = Arc::new(ArweaveSigner::from_keypair_bytes(jwk)?);
+        let everpay = Everpay::new(
+            EverpayClient::from_url_str(Client::new(), everpay_url)?,
+            signer,
+        )
+        .await?;
+
+        Self::from_url_str(
+            gateway,
+            Client::new(),
+            arweave_from_keypair_bytes(jwk, arweave_url)?,
             everpay,
+        )
+    }
+
+    // Builds a client from the environment:
+    // - ARSEEDING_URL, the gateway (default `DEFAULT_ARSEEDING_URL`)
+    // - EVERPAY_URL (default `DEFAULT_EVERPAY_URL`)
+    // - ARWEAVE_KEY_JSON, the arweave keyfile content, or ARWEAVE_KEYFILE, its
+    //   path. ARWEAVE_KEY_JSON is used when both are set.
+    // - ARSEEDING_API_KEY, optional api key sent with submissions
+    pub async fn from_env() -> Result<Self, ASError> {
+        Self::from_vars(|name| std::env::var(name).ok()).await
+    }
+
+    async fn from_vars<F>(var: F) -> Result<Self, ASError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let url_var = |name: &str, default: &str| {
+            let url = var(name).unwrap_or_else(|| default.to_string());
+            parse_url(&url).map_err(|_| ASError::ArgumentError {
+                arg: format!("{} is not a valid url: {}", name, url),
+            })
+        };
+        let url = url_var("ARSEEDING_URL", DEFAULT_ARSEEDING_URL)?;
+        let everpay_url = url_var("EVERPAY_URL", DEFAULT_EVERPAY_URL)?;
+
+        let jwk = match (var("ARWEAVE_KEY_JSON"), var("ARWEAVE_KEYFILE")) {
+            (Some(json), _) => json.into_bytes(),
+            (None, Some(path)) => std::fs::read(&path).map_err(|e| ASError::ArgumentError {
+                arg: format!("can't read ARWEAVE_KEYFILE {}: {}", path, e),
+            })?,
+            (None, None) => {
+                return Err(ASError::ArgumentError {
+                    arg: String::from("ARWEAVE_KEY_JSON or ARWEAVE_KEYFILE must be set"),
+                })
+            }
+        };
+        let arweave = arweave_from_keypair_bytes(&jwk, parse_url(DEFAULT_ARWEAVE_URL)?)?;
+
+        let signer = Arc::new(ArweaveSigner::from_keypair_bytes(&jwk)?);
+        let everpay = Everpay::new(EverpayClient::new(Client::new(), everpay_url), signer).await?;
+
+        let mut builder = Self::builder().url(url).arweave(arweave).everpay(everpay);
+        if let Some(api_key) = var("ARSEEDING_API_KEY") {
+            builder = builder.api_key(&api_key);
         }
+        builder.build()
     }
 
-    pub fn set_client(mut self, c: Client) {
+    pub fn builder() -> ASClientBuilder {
+        ASClientBuilder::default()
+    }
+
+    pub fn set_client(&mut self, c: Client) {
         self.client = c;
     }
 
+    pub fn http_client(&self) -> &Client {
+        &self.client
+    }
+
+    // counted before checking `shut_down`, so that `shutdown` either waits for
+    // the work or the work is refused
+    fn in_flight(&self) -> Result<InFlight<'_>, ASError> {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        let in_flight = InFlight { client: self };
+        if self.shut_down.load(Ordering::SeqCst) {
+            return Err(ASError::ShutDown);
+        }
+        Ok(in_flight)
+    }
+
+    // Refuses new uploads and payments with `ShutDown`, then waits for the ones
+    // in progress to finish. Call it before exiting so they aren't cut short,
+    // see `ASClient`.
+    pub async fn shutdown(&self) {
+        self.shut_down.store(true, Ordering::SeqCst);
+        loop {
+            let idle = self.idle.notified();
+            if self.in_flight.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+
+    // the arweave instance items are signed with. `Arweave::default()` has no
+    // usable keypair, which arloader only reports when signing fails.
+    fn arweave(&self) -> Result<&Arweave, ASError> {
+        let arweave = self.arweave.as_ref().ok_or_else(|| ASError::NoKeypair {
+            e: String::from("read only client"),
+        })?;
+        arweave
+            .crypto
+            .keypair_modulus()
+            .map_err(|e| ASError::NoKeypair { e: e.to_string() })?;
+
+        Ok(arweave)
+    }
+
+    fn everpay(&self) -> Result<&Everpay, ASError> {
+        self.everpay.as_ref().ok_or_else(|| ASError::ArgumentError {
+            arg: String::from("read only client, everpay is needed to pay"),
+        })
+    }
+
+    // an empty api key stands for the client's one, if it has one
+    fn api_key_or_default<'a>(&'a self, api_key: &'a str) -> &'a str {
+        match &self.api_key {
+            Some(k) if api_key.is_empty() => k,
+            _ => api_key,
+        }
+    }
+
+    // an empty currency stands for the client's default one, if it has one
+    fn currency_or_default<'a>(&'a self, currency: &'a str) -> &'a str {
+        match &self.default_currency {
+            Some(c) if currency.is_empty() => c,
+            _ => currency,
+        }
+    }
+
+    // the client's default tags (and `Unix-Time` and `Content-SHA256` if
+    // enabled) with the call's tags on top
+    fn with_default_tags(
+        &self,
+        data: &[u8],
+        tags: &HashMap<String, String>,
+    ) -> HashMap<String, String> {
+        let mut defaults = self.default_tags.clone();
+        if self.unix_time_tag {
+            defaults = tags::merge_tags(&defaults, &tags::unix_time_tag());
+        }
+        if self.content_sha256_tag {
+            defaults = tags::merge_tags(&defaults, &tags::content_sha256_tag(data));
+        }
+
+        tags::merge_tags(&defaults, tags)
+    }
+
+    // `ASError::from_response`, wrapped in `ASError::WithBodies` along with the
+    // request body, if any, when `capture_debug_bodies` is enabled
+    async fn error_from_response(&self, res: Response, request: Option<&[u8]>) -> ASError {
+        if !self.capture_debug_bodies {
+            return ASError::from_response(res).await;
+        }
+
+        let status = res.status();
+        let body = match res.bytes().await {
+            Ok(b) => b,
+            Err(e) => return ASError::from(e),
+        };
+        ASError::WithBodies {
+            e: Box::new(ASError::from_status_body(status, &body)),
+            request: request.map(|b| self.debug_body(b)),
+            response: self.debug_body(&body),
+        }
+    }
+
+    // `error_from_response` for the submission endpoints: a 401 or 403 to a
+    // request sent with an api key is the key being rejected, reported as
+    // `Unauthorized` so callers can ask for a new key or pay per upload. The
+    // gateway's other errors don't tell a bad key from a bad request.
+    async fn submission_error(
+        &self,
+        res: Response,
+        request: Option<&[u8]>,
+        api_key: &str,
+    ) -> ASError {
+        let rejected = !api_key.is_empty()
+            && matches!(
+                res.status(),
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+            );
+        let e = self.error_from_response(res, request).await;
+        if rejected {
+            return e.into_unauthorized();
+        }
+        e
+    }
+
+    // lossy utf8 of the first `DEBUG_BODY_LIMIT` bytes of a body, with the
+    // client's api key masked
+    fn debug_body(&self, body: &[u8]) -> String {
+        let mut s = String::from_utf8_lossy(&body[..body.len().min(DEBUG_BODY_LIMIT)]).to_string();
+        if body.len() > DEBUG_BODY_LIMIT {
+            s = format!("{}... ({} bytes)", s, body.len());
+        }
+        match &self.api_key {
+            Some(key) if !key.is_empty() => s.replace(key.as_str(), "[redacted]"),
+            _ => s,
+        }
+    }
+
     pub async fn get_bundler(&self) -> Result<BundlerRes, ASError> {
+        self.bundler_at(&self.url).await
+    }
+
+    // `get_bundler` of the node at `base_url`, e.g. a fallback gateway
+    async fn bundler_at(&self, base_url: &Url) -> Result<BundlerRes, ASError> {
         let res = self
             .client
-            .get(format!("{}{}", self.url, "bundle/bundler"))
+            .get(format!("{}{}", base_url, "bundle/bundler"))
             .send()
             .await?;
 
         match res.status() {
             StatusCode::OK => return Ok(res.json::<BundlerRes>().await?),
-            _ => {
-                return Err(ASError::APIError {
-                    e: res.json::<APIErrorRes>().await?.error,
-                })
-            }
+            _ => return Err(self.error_from_response(res, None).await),
         }
     }
 
+    // Signs `data` into an item and submits it, resubmitting the same item up to
+    // `submit_retries` times on transient failures (see the builder).
     pub async fn bundle_and_submit(
         &self,
         data: Vec<u8>,
         tags: &HashMap<String, String>,
         currency: &str,
         api_key: &str,
+        bundler: Option<&str>,
     ) -> Result<ItemSubmissionRes, ASError> {
-        let t: Vec<Tag<String>> = tags
+        let url = match bundler {
+            Some(b) => parse_url(b)?,
+            None => self.submission_url(),
+        };
+        // signed and hashed once, retries send the same bytes and item id
+        let item = Bytes::from(self.signed_item(data, tags).await?);
+        let digest = self.content_digest.then(|| content_digest(&item));
+
+        let mut attempt = 0;
+        loop {
+            let body = ItemBody {
+                body: Body::from(item.clone()),
+                len: item.len() as u64,
+                digest: digest.clone(),
+                request: Some(item.clone()),
+            };
+            let res = self.submit_item_body(&url, body, currency, api_key).await;
+            match res {
+                Err(e) if attempt < self.submit_retries && is_transient(&e) => {
+                    tokio::time::sleep(SUBMIT_BACKOFF.delay(attempt)).await;
+                    attempt += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    // Size in bytes of the item `bundle_and_submit` would upload for this data
+    // and tags, headers and tags included. Nothing is signed.
+    pub fn item_size(&self, data: &[u8], tags: &HashMap<String, String>) -> Result<usize, ASError> {
+        let tags = self.with_default_tags(data, tags);
+        match &self.item_signer {
+            Some(signer) => data_item::signed_data_item_len(
+                signer.as_ref(),
+                data.len(),
+                &tags::sorted_tags(&tags),
+                self.random_anchor,
+            ),
+            None => {
+                // signed with the arweave key, which a read only client lacks
+                self.arweave()?;
+                let tags = tags::with_guessed_content_type(data, tags);
+                Ok(data_item::data_item_len(
+                    data_item::SIG_TYPE_ARWEAVE,
+                    &data_item::encode_tags(&tags::sorted_tags(&tags)),
+                    data.len(),
+                    self.random_anchor,
+                ))
+            }
+        }
+    }
+
+    // serialized item signed with the client's `item_signer`, or its arweave
+    // key if it has none
+    async fn signed_item(
+        &self,
+        data: Vec<u8>,
+        tags: &HashMap<String, String>,
+    ) -> Result<Vec<u8>, ASError> {
+        let anchor = self.item_anchor()?;
+        let signer = match &self.item_signer {
+            Some(signer) => signer,
+            None => return self.arweave_signed_item(data, tags, &anchor),
+        };
+
+        let tags = self.with_default_tags(&data, tags);
+        tags::validate_tags(&tags)?;
+        data_item::sign_data_item(signer.as_ref(), &data, &tags::sorted_tags(&tags), &anchor).await
+    }
+
+    // random with `random_anchor`, empty (no anchor) otherwise
+    fn item_anchor(&self) -> Result<Vec<u8>, ASError> {
+        if !self.random_anchor {
+            return Ok(vec![]);
+        }
+        Ok(data_item::random_anchor()?.to_vec())
+    }
+
+    fn arweave_signed_item(
+        &self,
+        data: Vec<u8>,
+        tags: &HashMap<String, String>,
+        anchor: &[u8],
+    ) -> Result<Vec<u8>, ASError> {
+        let tags = self.with_default_tags(&data, tags);
+        tags::validate_tags(&tags)?;
+        // guessed here rather than by arloader so `item_size` can count it
+        let tags = tags::with_guessed_content_type(&data, tags);
+        let t = tags::sorted_tags(&tags)
             .iter()
-            .map(|(k, v)| Tag::from_utf8_strs(k, v).unwrap())
-            .collect();
+            .map(|(k, v)| Tag::from_utf8_strs(k, v))
+            .collect::<Result<Vec<Tag<String>>, _>>()?;
+
+        let arweave = self.arweave()?;
+        let mut data_item = arweave.create_data_item(data, t, false)?;
+        if !anchor.is_empty() {
+            data_item.anchor = Base64(anchor.to_vec());
+        }
+        let signed = arweave.sign_data_item(data_item)?;
+
+        Ok(signed.serialize()?)
+    }
+
+    // serializes `value` as json and submits it as a bundle item tagged
+    // `Content-Type: application/json`
+    pub async fn submit_json<T: Serialize>(
+        &self,
+        value: &T,
+        tags: &HashMap<String, String>,
+        currency: &str,
+        api_key: &str,
+    ) -> Result<ItemSubmissionRes, ASError> {
+        let data = serde_json::to_vec(value).map_err(|e| ASError::ArgumentError {
+            arg: format!("value can't be serialized to json: {}", e),
+        })?;
+        let tags = tags::merge_tags(tags, &tags::content_type_tag(tags::CONTENT_TYPE_JSON));
+
+        self.bundle_and_submit(data, &tags, currency, api_key, None)
+            .await
+    }
 
-        let data_item = self.arweave.create_data_item(data, t, true)?;
-        let signed = self.arweave.sign_data_item(data_item)?;
+    // same as `submit_json` with cbor, which is usually smaller hence cheaper
+    // to store. The item is tagged `Content-Type: application/cbor`.
+    #[cfg(feature = "cbor")]
+    pub async fn submit_cbor<T: Serialize>(
+        &self,
+        value: &T,
+        tags: &HashMap<String, String>,
+        currency: &str,
+        api_key: &str,
+    ) -> Result<ItemSubmissionRes, ASError> {
+        let data = serde_cbor::to_vec(value).map_err(|e| ASError::ArgumentError {
+            arg: format!("value can't be serialized to cbor: {}", e),
+        })?;
+        let tags = tags::merge_tags(tags, &tags::content_type_tag(tags::CONTENT_TYPE_CBOR));
 
-        self.submit_item(signed.serialize()?, currency, api_key)
+        self.bundle_and_submit(data, &tags, currency, api_key, None)
             .await
     }
 
+    // Cancellation: dropping the returned future is safe, nothing is spawned in
+    // the background. Depending on when it's dropped the item may have been
+    // accepted by the gateway without being paid for, in which case the order
+    // simply expires after `payment_expired_time`. If it's dropped while the
+    // everpay transfer is in flight the payment may or may not have gone
+    // through, check the order's `payment_status` before retrying (an
+    // `idempotency_cache` does it on the retry).
     pub async fn send_and_pay(
         &self,
         currency: &str,
@@ -81,53 +931,475 @@ impl ASClient {
         data: Vec<u8>,
         api_key: &str,
     ) -> Result<String, ASError> {
+        self.send_and_pay_with_fee(currency, tags, data, api_key, None)
+            .await
+    }
+
+    // `send_and_pay` paying `fee_override` instead of the quoted fee, e.g. to
+    // tip the bundler. It's in the currency's base units as quoted by the
+    // gateway and must be at least the quoted fee, else `FeeTooLow` is
+    // returned without paying. `max_fee` applies to it as to any fee.
+    pub async fn send_and_pay_with_fee(
+        &self,
+        currency: &str,
+        tags: &HashMap<String, String>,
+        data: Vec<u8>,
+        api_key: &str,
+        fee_override: Option<u64>,
+    ) -> Result<String, ASError> {
+        let _in_flight = self.in_flight()?;
+        let currency = self.currency_or_default(currency);
+        let idempotency_key = idempotency_key(&data, tags, currency);
+        if let Some(c) = &self.idempotency {
+            if let Some(item_id) = c.get(&idempotency_key) {
+                return Ok(item_id);
+            }
+            if let Some(pending) = c.pending(&idempotency_key) {
+                if let Some(item_id) = self.settle_pending(c, &idempotency_key, pending).await? {
+                    return Ok(item_id);
+                }
+            }
+        }
+
+        // fail before uploading anything if everpay can't pay in this currency
+        self.everpay_token(currency)?;
+
+        // The fee is only asked for its decimals, they don't depend on the size.
+        // Asked to the node the item goes to, before uploading so a failed
+        // quote doesn't leave an unpaid order.
+        let url = self.submission_url();
+        let quote = self.bundle_fee_at(&url, "0", currency).await?;
         let order = self
-            .bundle_and_submit(data, tags, currency, api_key)
+            .bundle_and_submit(data, tags, currency, api_key, Some(url.as_str()))
             .await?;
-
+        reconcile_decimals(&quote, &order)?;
+        let order = match fee_override {
+            Some(fee) => with_fee_override(&order, fee)?,
+            None => order,
+        };
+
+        // recorded first: if the transfer errors after everpay accepted it, a
+        // retry finds the order paid instead of paying it again
+        if let Some(c) = &self.idempotency {
+            let pending = PendingOrder {
+                base_url: url.to_string(),
+                order: order.clone(),
+            };
+            c.insert_pending(idempotency_key.clone(), pending);
+        }
+        self.pay_orders(&url, std::slice::from_ref(&order)).await?;
         let order_id = order.item_id;
 
-        // pay for tx using everpay
-        let fee = order.fee;
-        let fee_int: u64 = fee.parse().unwrap();
-        let bundler = order.bundler;
-        let currency = order.currency;
+        if let Some(c) = &self.idempotency {
+            c.insert(idempotency_key, order_id.clone());
+        }
 
-        let data = serde_json::to_string(&PayTxData {
-            app_name: String::from("arseeding"),
-            action: String::from("payment"),
-            item_ids: vec![order_id.clone()],
-        })
-        .unwrap();
+        Ok(order_id)
+    }
 
-        self.everpay
-            .transfer(&currency, &bundler, fee_int, &data)
-            .await?;
+    // Finishes a `send_and_pay` whose payment may not have gone through, going
+    // by the order's `payment_status` on the node it was submitted to: a paid
+    // order is recorded as is, an unpaid one is paid without uploading the
+    // item again. Returns None, after dropping the pending entry, when the
+    // order expired or the node doesn't know it: the item has to be uploaded
+    // again.
+    async fn settle_pending(
+        &self,
+        cache: &IdempotencyCache,
+        key: &str,
+        pending: PendingOrder,
+    ) -> Result<Option<String>, ASError> {
+        let PendingOrder { base_url, order } = pending;
+        let base_url = parse_url(&base_url)?;
+        let status = self
+            .find_order(&base_url, &order.item_id)
+            .await?
+            .map(|o| o.payment_status());
+        match status {
+            Some(PaymentStatus::Paid) => {}
+            // the gateway only sees a payment once everpay processed it, a
+            // retry right after a failed transfer can still pay twice
+            Some(PaymentStatus::Unpaid) if !order.is_expired() => {
+                self.pay_orders(&base_url, std::slice::from_ref(&order))
+                    .await?
+            }
+            _ => {
+                cache.remove_pending(key);
+                return Ok(None);
+            }
+        }
 
-        Ok(order_id)
+        cache.insert(key.to_string(), order.item_id.clone());
+        Ok(Some(order.item_id))
     }
 
-    pub async fn submit_item(
+    // order of `item_id` among the signer's orders on the `base_url` node, see
+    // `orders_paginated`
+    async fn find_order(&self, base_url: &Url, item_id: &str) -> Result<Option<OrderRes>, ASError> {
+        let signer = self.item_signer_address()?;
+        let mut pages = Box::pin(self.orders_paginated_at(base_url, signer));
+        while let Some(orders) = pages.try_next().await? {
+            if let Some(o) = orders.into_iter().find(|o| o.item_id == item_id) {
+                return Ok(Some(o));
+            }
+        }
+        Ok(None)
+    }
+
+    // Like `send_and_pay` for several files, e.g. a whole collection. Every
+    // file is measured first and the call fails before uploading anything if
+    // one is over `max_item_size`. The files are then split into batches of at
+    // most `SUBMIT_BATCH_LEN` items and `max_item_size` bytes, each batch is
+    // submitted then paid for with one everpay transfer (or more, see
+    // `pay_orders`) before the next one. If an item can't be submitted its
+    // batch isn't paid, the previous batches are. Returns the item ids in the
+    // files' order.
+    pub async fn submit_many_and_pay(
         &self,
-        data: Vec<u8>,
+        files: Vec<(Vec<u8>, HashMap<String, String>)>,
         currency: &str,
         api_key: &str,
-    ) -> Result<ItemSubmissionRes, ASError> {
-        // TODO check currency
-
-        let mut url: String = "bundle/tx".to_string();
-        if currency.len() > 0 {
-            url = format!("{}{}{}", self.url, "bundle/tx/", currency);
+    ) -> Result<Vec<String>, ASError> {
+        let _in_flight = self.in_flight()?;
+        let currency = self.currency_or_default(currency);
+        if files.is_empty() {
+            return Ok(vec![]);
         }
 
-        let mut req = self
-            .client
-            .post(url)
-            .header("Content-Type", "application/octet-stream")
-            .body(data);
-
-        if api_key.len() > 0 {
-            req = req.header("X-API-KEY", api_key);
+        // fail before uploading anything if everpay can't pay in this currency
+        self.everpay_token(currency)?;
+
+        // or if a file is too large
+        let max = self.max_item_size();
+        let mut sizes = Vec::with_capacity(files.len());
+        for (data, tags) in &files {
+            let size = self.item_size(data, tags)? as u64;
+            if let Some(max) = max {
+                if size > max {
+                    return Err(ASError::ItemTooLarge { size, max });
+                }
+            }
+            sizes.push(size);
+        }
+
+        // every item goes to the same node, the one their payment and decimals
+        // are checked on
+        let url = self.submission_url();
+        // the fee is only asked for its decimals, see `send_and_pay_with_fee`
+        let quote = self.bundle_fee_at(&url, "0", currency).await?;
+        let mut files = files.into_iter();
+        let mut item_ids = Vec::with_capacity(sizes.len());
+        for batch in submit_batches(&sizes, max, SUBMIT_BATCH_LEN) {
+            let orders: Vec<ItemSubmissionRes> = stream::iter(files.by_ref().take(batch))
+                .map(|(data, tags)| {
+                    let url = url.as_str();
+                    async move {
+                        self.bundle_and_submit(data, &tags, currency, api_key, Some(url))
+                            .await
+                    }
+                })
+                .buffered(SUBMIT_CONCURRENCY)
+                .try_collect()
+                .await?;
+            for order in &orders {
+                reconcile_decimals(&quote, order)?;
+            }
+
+            self.pay_orders(&url, &orders).await?;
+            item_ids.extend(orders.into_iter().map(|o| o.item_id));
+        }
+
+        Ok(item_ids)
+    }
+
+    // Pays for `orders` with one everpay transfer, they must share the same
+    // bundler and currency, and the bundler must be the one advertised by
+    // `base_url`, the node they were submitted to (see
+    // `verify_gateway_everpay_match`). When the item ids don't fit in one
+    // transfer's data they're paid in several transfers, if one fails the
+    // previous ones have gone through.
+    async fn pay_orders(
+        &self,
+        base_url: &Url,
+        orders: &[ItemSubmissionRes],
+    ) -> Result<(), ASError> {
+        let first = match orders.first() {
+            Some(o) => o,
+            None => return Ok(()),
+        };
+        let bundler = &first.bundler;
+        let currency = &first.currency;
+
+        // the fee is quoted in the gateway's decimals, everpay expects the token's ones
+        let token_decimals = self.everpay_token(currency)?.decimals;
+        let mut fees = Vec::with_capacity(orders.len());
+        for order in orders {
+            if &order.bundler != bundler || &order.currency != currency {
+                return Err(ASError::ArgumentError {
+                    arg: format!(
+                        "item {} is paid to {} in {}, expected {} in {}",
+                        order.item_id, order.bundler, order.currency, bundler, currency
+                    ),
+                });
+            }
+
+            let fee: u64 = order.fee.parse().map_err(|_| ASError::ArgumentError {
+                arg: format!("invalid fee: {}", order.fee),
+            })?;
+            let fee = convert_decimals(fee, order.decimals, token_decimals).ok_or(
+                ASError::DecimalMismatch {
+                    currency: currency.clone(),
+                    item_decimals: order.decimals,
+                    token_decimals,
+                },
+            )?;
+            fees.push(fee);
+        }
+
+        verify_gateway_everpay_match(&self.bundler_at(base_url).await?, first)?;
+
+        // checked before any transfer, the limit is on the whole payment
+        if let Some(limit) = self.max_fees.get(&currency.to_lowercase()) {
+            let total = fees.iter().map(|f| *f as u128).sum();
+            check_fee_limit(currency, total, token_decimals, *limit)?;
+        }
+
+        let mut start = 0;
+        for batch in payment_batches(orders, self.everpay()?.max_tx_data_len()) {
+            let fees = &fees[start..start + batch.len()];
+            start += batch.len();
+
+            let total = fees
+                .iter()
+                .try_fold(0u64, |total, fee| total.checked_add(*fee))
+                .ok_or(ASError::ArgumentError {
+                    arg: String::from("total fee overflows"),
+                })?;
+
+            let data = serde_json::to_string(&PayTxData {
+                app_name: String::from("arseeding"),
+                action: String::from("payment"),
+                item_ids: batch.iter().map(|o| o.item_id.clone()).collect(),
+            })
+            .unwrap();
+
+            self.everpay()?
+                .transfer(currency, bundler, total, &data)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    // The gateway and everpay each have their own token list. While a token is
+    // being added they can disagree, refreshing everpay's list fixes it.
+    fn everpay_token(&self, currency: &str) -> Result<TokenList, ASError> {
+        self.everpay()?
+            .token_by_symbol(currency)
+            .ok_or_else(|| ASError::TokenError {
+                arg: format!(
+                    "{} is not in everpay's token list, call `refresh_everpay` and retry",
+                    currency
+                ),
+            })
+    }
+
+    // reloads everpay's token list
+    pub async fn refresh_everpay(&self) -> Result<(), ASError> {
+        match self.everpay.as_ref() {
+            Some(everpay) => everpay.refresh().await,
+            None => Ok(()),
+        }
+    }
+
+    // Pings the client's gateway and the extra ones set with
+    // `ASClientBuilder::gateways`, submissions then go to the fastest healthy
+    // one. Call it after building the client and from time to time (see
+    // `monitor_gateway_health`), until then the client's gateway is used.
+    pub async fn refresh_gateway_health(&self) -> Vec<GatewayHealth> {
+        let urls = std::iter::once(&self.url).chain(self.gateways.iter());
+        let checks = urls.map(|url| async move {
+            let start = Instant::now();
+            let res = self
+                .client
+                .get(format!("{}bundle/bundler", url))
+                .timeout(GATEWAY_HEALTH_TIMEOUT)
+                .send()
+                .await;
+            let healthy = matches!(res, Ok(r) if r.status() == StatusCode::OK);
+
+            GatewayHealth {
+                url: url.clone(),
+                healthy,
+                latency: healthy.then(|| start.elapsed()),
+            }
+        });
+
+        let ranked = rank_gateways(futures::future::join_all(checks).await);
+        *self.gateway_health.lock().unwrap() = ranked.clone();
+        ranked
+    }
+
+    // refreshes the gateways health every `interval`, never returns. Meant to
+    // be spawned next to the client or raced with a shutdown signal.
+    pub async fn monitor_gateway_health(&self, interval: Duration) {
+        loop {
+            self.refresh_gateway_health().await;
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    // the fastest healthy gateway as of the last health check, the client's
+    // gateway when none was done or no gateway is healthy
+    pub fn submission_url(&self) -> Url {
+        self.gateway_health
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|g| g.healthy)
+            .map(|g| g.url.clone())
+            .unwrap_or_else(|| self.url.clone())
+    }
+
+    // `bundler` is the base url of the arseeding node the item should be
+    // submitted to, the client's gateway is used when None
+    pub async fn submit_item(
+        &self,
+        data: Vec<u8>,
+        currency: &str,
+        api_key: &str,
+        bundler: Option<&str>,
+    ) -> Result<ItemSubmissionRes, ASError> {
+        let url = match bundler {
+            Some(b) => parse_url(b)?,
+            None => self.submission_url(),
+        };
+
+        let body = ItemBody::from_bytes(Bytes::from(data), self.content_digest);
+        self.submit_item_body(&url, body, currency, api_key).await
+    }
+
+    // Like `submit_item` for a signed item the caller supplies as a body (a
+    // stream, a file, bytes...) of `len` bytes, so it doesn't have to be held
+    // in memory. `len` must be the exact item size. No `Content-Digest` is sent
+    // as the body isn't read up front.
+    pub async fn submit_item_from_body(
+        &self,
+        body: Body,
+        len: u64,
+        currency: &str,
+        api_key: &str,
+        bundler: Option<&str>,
+    ) -> Result<ItemSubmissionRes, ASError> {
+        let url = match bundler {
+            Some(b) => parse_url(b)?,
+            None => self.submission_url(),
+        };
+
+        let body = ItemBody {
+            body,
+            len,
+            digest: None,
+            request: None,
+        };
+        self.submit_item_body(&url, body, currency, api_key).await
+    }
+
+    // Submits signed items as the producer yields them, a few at a time. Results
+    // come out in the same order as the items, nothing is submitted until the
+    // returned stream is polled.
+    pub fn submit_items_stream<'a>(
+        &'a self,
+        items: impl Stream<Item = Vec<u8>> + 'a,
+        currency: &'a str,
+        api_key: &'a str,
+    ) -> impl Stream<Item = Result<ItemSubmissionRes, ASError>> + 'a {
+        items
+            .map(move |item| self.submit_item(item, currency, api_key, None))
+            .buffered(SUBMIT_CONCURRENCY)
+    }
+
+    // same as `submit_item` but streams the item in chunks and calls
+    // `progress(bytes_sent, total)` every time a chunk is handed to the connection.
+    // Dropping the future aborts the upload and releases the body and callback,
+    // a partially sent item is discarded by the gateway.
+    pub async fn submit_item_with_progress<F>(
+        &self,
+        data: Vec<u8>,
+        currency: &str,
+        api_key: &str,
+        progress: F,
+    ) -> Result<ItemSubmissionRes, ASError>
+    where
+        F: Fn(u64, u64) + Send + Sync + 'static,
+    {
+        let data = Bytes::from(data);
+        let total = data.len() as u64;
+        let digest = self.content_digest.then(|| content_digest(&data));
+
+        let chunks: Vec<Bytes> = (0..data.len())
+            .step_by(UPLOAD_CHUNK_SIZE)
+            .map(|i| data.slice(i..data.len().min(i + UPLOAD_CHUNK_SIZE)))
+            .collect();
+
+        let mut sent = 0;
+        let stream = stream::iter(chunks).map(move |c| {
+            sent += c.len() as u64;
+            progress(sent, total);
+            Ok::<Bytes, std::io::Error>(c)
+        });
+
+        let body = ItemBody {
+            body: Body::wrap_stream(stream),
+            len: total,
+            digest,
+            request: Some(data),
+        };
+        self.submit_item_body(&self.submission_url(), body, currency, api_key)
+            .await
+    }
+
+    async fn submit_item_body(
+        &self,
+        base_url: &Url,
+        item: ItemBody,
+        currency: &str,
+        api_key: &str,
+    ) -> Result<ItemSubmissionRes, ASError> {
+        let ItemBody {
+            body,
+            len,
+            digest,
+            request,
+        } = item;
+        let _in_flight = self.in_flight()?;
+        // TODO check currency
+        let currency = self.currency_or_default(currency);
+
+        if let Some(max) = self.max_item_size() {
+            if len > max {
+                return Err(ASError::ItemTooLarge { size: len, max });
+            }
+        }
+
+        let mut url = format!("{}{}", base_url, "bundle/tx");
+        if currency.len() > 0 {
+            url = format!("{}{}{}", base_url, "bundle/tx/", currency);
+        }
+
+        let mut req = self
+            .client
+            .post(url)
+            .header("Content-Type", tags::CONTENT_TYPE_OCTET_STREAM)
+            .header("Content-Length", len)
+            .body(body);
+
+        if let Some(digest) = digest {
+            req = req.header("Content-Digest", digest);
+        }
+        let api_key = self.api_key_or_default(api_key);
+        if api_key.len() > 0 {
+            req = req.header("X-API-KEY", api_key);
         }
 
         let res = req.send().await?;
@@ -135,141 +1407,1649 @@ impl ASClient {
         match res.status() {
             StatusCode::OK => return Ok(res.json::<ItemSubmissionRes>().await?),
             _ => {
-                return Err(ASError::APIError {
-                    e: res.json::<APIErrorRes>().await?.error,
+                return Err(self
+                    .submission_error(res, request.as_deref(), api_key)
+                    .await)
+            }
+        }
+    }
+
+    // Stores `data` as is, the gateway wraps it in an item signed with its own
+    // key. The item is then owned by the bundler, `get_item_meta(item_id).owner`
+    // is the bundler's key. `submit_native_owned` keeps the caller as owner.
+    pub async fn submit_native_data(
+        &self,
+        data: Vec<u8>,
+        content_type: &str,
+        filename: Option<&str>,
+        tags: &HashMap<String, String>,
+        api_key: &str,
+    ) -> Result<SubmitNativeRes, ASError> {
+        let _in_flight = self.in_flight()?;
+        // tags passed by the caller win over the filename one
+        let tags = match filename {
+            Some(f) => tags::merge_tags(&tags::filename_tag(f), tags),
+            None => tags.clone(),
+        };
+        let tags = self.with_default_tags(&data, &tags);
+        let digest = self.content_digest.then(|| content_digest(&data));
+        let data = Bytes::from(data);
+
+        let mut req = self
+            .client
+            .post(format!("{}{}", self.submission_url(), "bundle/data"))
+            .header("Content-Type", content_type)
+            .query(&[(tags::CONTENT_TYPE, content_type)])
+            .query(&tags)
+            .body(data.clone());
+
+        if let Some(digest) = digest {
+            req = req.header("Content-Digest", digest);
+        }
+        let api_key = self.api_key_or_default(api_key);
+        if api_key.len() > 0 {
+            req = req.header("X-API-KEY", api_key);
+        }
+
+        let res = req.send().await?;
+
+        match res.status() {
+            StatusCode::OK => return Ok(res.json::<SubmitNativeRes>().await?),
+            _ => return Err(self.submission_error(res, Some(&data), api_key).await),
+        }
+    }
+
+    // Same arguments as `submit_native_data`, but the item is signed by this
+    // client (`item_signer` or the arweave keypair) so the caller owns it. As
+    // any signed item it's an order to pay, unless `api_key` covers it.
+    pub async fn submit_native_owned(
+        &self,
+        data: Vec<u8>,
+        content_type: &str,
+        filename: Option<&str>,
+        tags: &HashMap<String, String>,
+        currency: &str,
+        api_key: &str,
+    ) -> Result<ItemSubmissionRes, ASError> {
+        let mut item_tags = tags::content_type_tag(content_type);
+        if let Some(f) = filename {
+            item_tags = tags::merge_tags(&item_tags, &tags::filename_tag(f));
+        }
+        // tags passed by the caller win, as with `submit_native_data`
+        let tags = tags::merge_tags(&item_tags, tags);
+
+        self.bundle_and_submit(data, &tags, currency, api_key, None)
+            .await
+    }
+
+    // Whether this client's key owns the item, e.g. false for native data
+    // stored with `submit_native_data` since the bundler signs it.
+    pub async fn is_own_item(&self, item_id: &str) -> Result<bool, ASError> {
+        let (meta, owner) = futures::try_join!(self.get_item_meta(item_id), self.item_owner())?;
+        Ok(meta.owner == Base64(owner).to_string())
+    }
+
+    // address the gateway files the orders of this client's items under: the
+    // `item_signer`'s when set, else the arweave key's
+    fn item_signer_address(&self) -> Result<String, ASError> {
+        match &self.item_signer {
+            Some(signer) => signer.wallet_address(),
+            None => Ok(self.arweave()?.crypto.wallet_address()?.to_string()),
+        }
+    }
+
+    // owner of the items this client signs
+    async fn item_owner(&self) -> Result<Vec<u8>, ASError> {
+        match &self.item_signer {
+            Some(signer) => signer.item_owner().await,
+            None => Ok(self.arweave()?.crypto.keypair_modulus()?.0),
+        }
+    }
+
+    pub async fn submit_native_multipart(
+        &self,
+        field_name: &str,
+        filename: &str,
+        data: Vec<u8>,
+        content_type: &str,
+        tags: &HashMap<String, String>,
+        api_key: &str,
+    ) -> Result<SubmitNativeRes, ASError> {
+        let _in_flight = self.in_flight()?;
+        let tags = self.with_default_tags(&data, tags);
+        let part = multipart::Part::bytes(data)
+            .file_name(filename.to_string())
+            .mime_str(content_type)?;
+        let form = multipart::Form::new().part(field_name.to_string(), part);
+
+        let mut req = self
+            .client
+            .post(format!("{}{}", self.submission_url(), "bundle/data"))
+            .query(&[(tags::CONTENT_TYPE, content_type)])
+            .query(&tags)
+            .multipart(form);
+
+        let api_key = self.api_key_or_default(api_key);
+        if api_key.len() > 0 {
+            req = req.header("X-API-KEY", api_key);
+        }
+
+        let res = req.send().await?;
+
+        match res.status() {
+            StatusCode::OK => return Ok(res.json::<SubmitNativeRes>().await?),
+            _ => return Err(self.submission_error(res, None, api_key).await),
+        }
+    }
+
+    pub async fn get_bundle_fee(&self, size: &str, currency: &str) -> Result<FeeRes, ASError> {
+        self.bundle_fee_at(&self.url, size, currency).await
+    }
+
+    async fn bundle_fee_at(
+        &self,
+        base_url: &Url,
+        size: &str,
+        currency: &str,
+    ) -> Result<FeeRes, ASError> {
+        let currency = self.currency_or_default(currency);
+        let res = self
+            .client
+            .get(format!("{}bundle/fee/{}/{}", base_url, size, currency))
+            .send()
+            .await?;
+
+        match res.status() {
+            StatusCode::OK => return Ok(res.json::<FeeRes>().await?),
+            _ => return Err(self.error_from_response(res, None).await),
+        }
+    }
+
+    // quotes several sizes at once, results are in the same order as `sizes`.
+    // arweave pricing isn't linear in the size (it's charged per 256KiB chunk
+    // plus a base fee) so each size is asked to the gateway, concurrently.
+    pub async fn estimate_fees(
+        &self,
+        sizes: &[usize],
+        currency: &str,
+    ) -> Result<Vec<FeeRes>, ASError> {
+        let sizes: Vec<String> = sizes.iter().map(|s| s.to_string()).collect();
+
+        try_join_all(sizes.iter().map(|s| self.get_bundle_fee(s, currency))).await
+    }
+
+    // whether `account_id` holds enough `currency` on everpay to pay for an
+    // upload of `bytes` bytes, transfer fee included
+    pub async fn can_afford(
+        &self,
+        account_id: &str,
+        bytes: usize,
+        currency: &str,
+    ) -> Result<bool, ASError> {
+        let currency = self.currency_or_default(currency);
+        let token = self.everpay_token(currency)?;
+
+        let size = bytes.to_string();
+        let (fee, balances) = futures::try_join!(
+            self.get_bundle_fee(&size, currency),
+            self.everpay()?.balances(account_id)
+        )?;
+        let cost = upload_cost(&fee, &token)?;
+
+        // no entry is a zero balance, see `Balances::get`
+        let balance = match balances.get(&token.tag) {
+            Some(b) => b
+                .amount
+                .parse::<u128>()
+                .map_err(|_| ASError::ArgumentError {
+                    arg: format!("invalid balance: {}", b.amount),
+                })?,
+            None => 0,
+        };
+
+        Ok(balance >= cost as u128)
+    }
+
+    // Largest item submitted, as set on the builder. Arseeding doesn't
+    // document a way to ask a gateway for its limit.
+    pub fn max_item_size(&self) -> Option<u64> {
+        self.max_item_size
+    }
+
+    pub async fn get_bundler_orders(
+        &self,
+        signer: &str,
+        cursor: &str,
+    ) -> Result<Vec<OrderRes>, ASError> {
+        self.get_bundler_orders_sorted(signer, cursor, "", None)
+            .await
+    }
+
+    // Like `get_bundler_orders` with the page sorted by the gateway on the
+    // `sort_by` field (e.g. `"id"`), in `order` direction. An empty `sort_by`
+    // or a None `order` leaves the gateway's default.
+    //
+    //     // last 10 uploads
+    //     let orders = c.get_bundler_orders_sorted(&signer, "", "id", Some(SortOrder::Desc)).await?;
+    //     let last = &orders[..orders.len().min(10)];
+    pub async fn get_bundler_orders_sorted(
+        &self,
+        signer: &str,
+        cursor: &str,
+        sort_by: &str,
+        order: Option<SortOrder>,
+    ) -> Result<Vec<OrderRes>, ASError> {
+        self.bundler_orders_at(&self.url, signer, cursor, sort_by, order)
+            .await
+    }
+
+    // `get_bundler_orders_sorted` on the node at `base_url`
+    async fn bundler_orders_at(
+        &self,
+        base_url: &Url,
+        signer: &str,
+        cursor: &str,
+        sort_by: &str,
+        order: Option<SortOrder>,
+    ) -> Result<Vec<OrderRes>, ASError> {
+        let mut req = self
+            .client
+            .get(format!("{}bundle/orders/{}", base_url, signer));
+
+        if cursor.len() > 0 {
+            req = req.query(&[("cursor", cursor)]);
+        }
+        if !sort_by.is_empty() {
+            req = req.query(&[("sort", sort_by)]);
+        }
+        if let Some(order) = order {
+            req = req.query(&[("order", order.as_str())]);
+        }
+
+        let res = req.send().await?;
+
+        match res.status() {
+            StatusCode::OK => return Ok(res.json::<Vec<OrderRes>>().await?),
+            _ => return Err(self.error_from_response(res, None).await),
+        }
+    }
+
+    // Orders of `signer` a page at a time, e.g. to load them as a list is
+    // scrolled. Pages are fetched with the id of the previous page's last order
+    // as cursor until one comes back empty, nothing is fetched until the
+    // stream is polled and dropping it stops the paging.
+    pub fn orders_paginated(
+        &self,
+        signer: String,
+    ) -> impl Stream<Item = Result<Vec<OrderRes>, ASError>> + '_ {
+        self.orders_paginated_at(&self.url, signer)
+    }
+
+    // `orders_paginated` on the node at `base_url`
+    fn orders_paginated_at<'a>(
+        &'a self,
+        base_url: &'a Url,
+        signer: String,
+    ) -> impl Stream<Item = Result<Vec<OrderRes>, ASError>> + 'a {
+        stream::try_unfold(String::new(), move |cursor| {
+            let signer = signer.clone();
+            async move {
+                let orders = self
+                    .bundler_orders_at(base_url, &signer, &cursor, "", None)
+                    .await?;
+                let last = match orders.last() {
+                    Some(o) => o.id.to_string(),
+                    None => return Ok(None),
+                };
+                // a gateway ignoring the cursor would send the same page forever
+                if last == cursor {
+                    return Ok(None);
+                }
+                Ok(Some((orders, last)))
+            }
+        })
+    }
+
+    // Writes every order of `signer` to `writer` as JSON lines, a page at a
+    // time (see `orders_paginated`), and returns how many were written.
+    pub async fn export_orders(
+        &self,
+        signer: &str,
+        mut writer: impl AsyncWrite + Unpin,
+    ) -> Result<usize, ASError> {
+        let mut count = 0;
+        let mut pages = Box::pin(self.orders_paginated(signer.to_string()));
+        while let Some(orders) = pages.try_next().await? {
+            for order in &orders {
+                let mut line = serde_json::to_vec(order)?;
+                line.push(b'\n');
+                writer.write_all(&line).await?;
+            }
+            count += orders.len();
+        }
+
+        writer.flush().await?;
+        Ok(count)
+    }
+
+    pub async fn get_item_meta(&self, item_id: &str) -> Result<ItemMetaRes, ASError> {
+        let res = self
+            .client
+            .get(format!("{}bundle/tx/{}", self.url, item_id))
+            .send()
+            .await?;
+
+        match res.status() {
+            StatusCode::OK => {
+                return Ok(serde_json::from_slice::<ItemMetaRes>(
+                    &self.read_body(res).await?,
+                )?)
+            }
+            _ => return Err(self.error_from_response(res, None).await),
+        }
+    }
+
+    pub async fn get_data(&self, item_id: &str) -> Result<Vec<u8>, ASError> {
+        let res = self
+            .client
+            .get(format!("{}bundle/tx/{}/data", self.url, item_id))
+            .send()
+            .await?;
+
+        match res.status() {
+            StatusCode::OK => return self.read_body(res).await,
+            _ => return Err(self.error_from_response(res, None).await),
+        }
+    }
+
+    // Whether the gateway has the item, with a HEAD request on its data so
+    // nothing is downloaded. Content addressed uploads can be skipped when
+    // their item id already exists.
+    pub async fn exists(&self, item_id: &str) -> Result<bool, ASError> {
+        let res = self
+            .client
+            .head(format!("{}bundle/tx/{}/data", self.url, item_id))
+            .send()
+            .await?;
+
+        match res.status() {
+            StatusCode::OK => return Ok(true),
+            StatusCode::NOT_FOUND => return Ok(false),
+            _ => return Err(self.error_from_response(res, None).await),
+        }
+    }
+
+    // Downloads the items' data a few at a time. Each item comes out with its
+    // own result as soon as it's downloaded, so not in the order of `ids`, and
+    // a failed download doesn't stop the others.
+    pub fn get_data_many(
+        &self,
+        ids: Vec<String>,
+    ) -> impl Stream<Item = (String, Result<Vec<u8>, ASError>)> + '_ {
+        stream::iter(ids)
+            .map(move |id| async move {
+                let res = self.get_data(&id).await;
+                (id, res)
+            })
+            .buffer_unordered(DOWNLOAD_CONCURRENCY)
+    }
+
+    // bytes `start` to `end` (both inclusive, as in the http Range header) of an
+    // item's data. Gateways that ignore the header send the whole data, which
+    // is cut down to the range.
+    pub async fn get_data_range(
+        &self,
+        item_id: &str,
+        start: u64,
+        end: u64,
+    ) -> Result<Vec<u8>, ASError> {
+        if start > end {
+            return Err(ASError::ArgumentError {
+                arg: format!("invalid range: {}-{}", start, end),
+            });
+        }
+
+        let res = self
+            .client
+            .get(format!("{}bundle/tx/{}/data", self.url, item_id))
+            .header("Range", format!("bytes={}-{}", start, end))
+            .send()
+            .await?;
+
+        match res.status() {
+            StatusCode::PARTIAL_CONTENT => return self.read_body(res).await,
+            StatusCode::OK => {
+                let data = self.read_body(res).await?;
+                let len = data.len() as u64;
+                let start = start.min(len) as usize;
+                let end = end.saturating_add(1).min(len) as usize;
+                return Ok(data[start..end].to_vec());
+            }
+            StatusCode::RANGE_NOT_SATISFIABLE => {
+                return Err(ASError::ArgumentError {
+                    arg: format!("range not satisfiable: {}-{}", start, end),
+                })
+            }
+            _ => return Err(self.error_from_response(res, None).await),
+        }
+    }
+
+    // reads the whole body, giving up as soon as it's bigger than the
+    // configured max response size
+    async fn read_body(&self, mut res: Response) -> Result<Vec<u8>, ASError> {
+        let max = match self.max_response_size {
+            Some(max) => max,
+            None => return Ok(res.bytes().await?.to_vec()),
+        };
+
+        let too_large = || ASError::ResponseTooLarge { max };
+        if res.content_length().unwrap_or(0) > max as u64 {
+            return Err(too_large());
+        }
+
+        let mut body = Vec::new();
+        while let Some(chunk) = res.chunk().await? {
+            if body.len() + chunk.len() > max {
+                return Err(too_large());
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        Ok(body)
+    }
+
+    // raw data of an arweave transaction (not a bundle item), served by the gateway
+    pub async fn get_ar_tx_data(&self, ar_id: &str) -> Result<Vec<u8>, ASError> {
+        let res = self
+            .client
+            .get(format!("{}{}", self.url, ar_id))
+            .send()
+            .await?;
+
+        match res.status() {
+            StatusCode::OK => return self.read_body(res).await,
+            _ => return Err(self.error_from_response(res, None).await),
+        }
+    }
+
+    // Where the item is on its way to arweave, looked up through the arweave
+    // gateway's graphql index. Confirmations are counted from the block the
+    // item's bundle was mined in.
+    pub async fn seeding_status(&self, item_id: &str) -> Result<SeedingStatus, ASError> {
+        let query = json!({
+            "query": "query($id: ID!) { transaction(id: $id) { bundledIn { id } block { height } } }",
+            "variables": { "id": item_id },
+        });
+
+        let res = self
+            .client
+            .post(format!("{}graphql", self.arweave_url))
+            .json(&query)
+            .send()
+            .await?;
+
+        let tx = match res.status() {
+            StatusCode::OK => res.json::<GraphQLTxRes>().await?.data.transaction,
+            _ => {
+                let query = query.to_string();
+                return Err(self.error_from_response(res, Some(query.as_bytes())).await);
+            }
+        };
+
+        let block = match tx {
+            None => return Ok(SeedingStatus::Pending),
+            Some(GraphQLTx { block: None, .. }) => return Ok(SeedingStatus::Seeded),
+            Some(GraphQLTx {
+                block: Some(block), ..
+            }) => block,
+        };
+
+        let height = self.arweave_height().await?;
+        let confirmations = height.saturating_sub(block.height) + 1;
+        Ok(SeedingStatus::Confirmed {
+            confirmations: u32::try_from(confirmations).unwrap_or(u32::MAX),
+        })
+    }
+
+    // current arweave block height
+    async fn arweave_height(&self) -> Result<u64, ASError> {
+        let res = self
+            .client
+            .get(format!("{}info", self.arweave_url))
+            .send()
+            .await?;
+
+        match res.status() {
+            StatusCode::OK => return Ok(res.json::<NetworkInfoRes>().await?.height),
+            _ => return Err(self.error_from_response(res, None).await),
+        }
+    }
+
+    // Backlog of the client's own uploads: its latest orders that are paid but
+    // not seeded yet, and when the bundler expects to be done with them.
+    // Arseeding doesn't expose the bundler's global queue.
+    pub async fn bundler_backlog(&self) -> Result<BacklogInfo, ASError> {
+        let signer = self.item_signer_address()?;
+        let (orders, height) =
+            futures::try_join!(self.get_bundler_orders(&signer, ""), self.arweave_height())?;
+
+        Ok(backlog(&orders, height))
+    }
+
+    pub async fn get_items_by_ar_id(&self, ar_id: &str) -> Result<Vec<String>, ASError> {
+        let res = self
+            .client
+            .get(format!("{}bundle/itemIds/{}", self.url, ar_id))
+            .send()
+            .await?;
+
+        match res.status() {
+            StatusCode::OK => return Ok(res.json::<Vec<String>>().await?),
+            _ => return Err(self.error_from_response(res, None).await),
+        }
+    }
+
+    // Smoke test of the gateway: uploads a small unique payload, pays for it,
+    // waits for the gateway to see the payment and downloads the data back.
+    // Costs one upload fee. Fails with `APIError` if the data differs.
+    pub async fn self_test(
+        &self,
+        currency: &str,
+        api_key: &str,
+    ) -> Result<SelfTestReport, ASError> {
+        let _in_flight = self.in_flight()?;
+        let currency = self.currency_or_default(currency);
+        self.everpay_token(currency)?;
+        let signer = self.item_signer_address()?;
+
+        let nanos = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let data = format!("arseeding self test {}", nanos).into_bytes();
+        let tags = tags::content_type_tag("text/plain");
+
+        let start = Instant::now();
+        let url = self.submission_url();
+        let order = self
+            .bundle_and_submit(data.clone(), &tags, currency, api_key, Some(url.as_str()))
+            .await?;
+        let upload = start.elapsed();
+
+        let start = Instant::now();
+        self.pay_orders(&url, std::slice::from_ref(&order)).await?;
+        let payment = start.elapsed();
+
+        let start = Instant::now();
+        poll_until(
+            || self.bundler_orders_at(&url, &signer, "", "", None),
+            |orders| {
+                orders.iter().any(|o| {
+                    o.item_id == order.item_id && o.payment_status() == PaymentStatus::Paid
                 })
+            },
+            SELF_TEST_PAYMENT_TIMEOUT,
+            Backoff::default(),
+        )
+        .await?;
+        let confirmation = start.elapsed();
+
+        let start = Instant::now();
+        let downloaded = self.get_data(&order.item_id).await?;
+        let download = start.elapsed();
+
+        if downloaded != data {
+            return Err(ASError::APIError {
+                e: format!(
+                    "item {} data doesn't match the upload: {} bytes sent, {} received",
+                    order.item_id,
+                    data.len(),
+                    downloaded.len()
+                ),
+            });
+        }
+
+        Ok(SelfTestReport {
+            item_id: order.item_id,
+            upload,
+            payment,
+            confirmation,
+            download,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct ASClientBuilder {
+    url: Option<Url>,
+    arweave: Option<Arweave>,
+    everpay: Option<Everpay>,
+    pool_max_idle_per_host: Option<usize>,
+    pool_idle_timeout: Option<Duration>,
+    tcp_keepalive: Option<Duration>,
+    idempotency: Option<IdempotencyCache>,
+    default_tags: HashMap<String, String>,
+    unix_time_tag: bool,
+    content_sha256_tag: bool,
+    capture_debug_bodies: bool,
+    max_response_size: Option<usize>,
+    root_certificates: Vec<Certificate>,
+    accept_invalid_certs: bool,
+    proxies: Vec<Proxy>,
+    gateways: Vec<Url>,
+    max_fees: HashMap<String, Decimal>,
+    default_currency: Option<String>,
+    content_digest: bool,
+    api_key: Option<String>,
+    max_item_size: Option<u64>,
+    submit_retries: u32,
+    item_signer: Option<Arc<dyn Signer + Send + Sync>>,
+    random_anchor: bool,
+}
+
+impl ASClientBuilder {
+    pub fn url(mut self, url: Url) -> Self {
+        self.url = Some(url);
+        self
+    }
+
+    // fallback gateways, used instead of `url` when they answer faster or `url`
+    // is down. See `ASClient::refresh_gateway_health`.
+    pub fn gateways(mut self, gateways: Vec<Url>) -> Self {
+        self.gateways = gateways;
+        self
+    }
+
+    pub fn arweave(mut self, arweave: Arweave) -> Self {
+        self.arweave = Some(arweave);
+        self
+    }
+
+    pub fn everpay(mut self, everpay: Everpay) -> Self {
+        self.everpay = Some(everpay);
+        self
+    }
+
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    pub fn tcp_keepalive(mut self, interval: Duration) -> Self {
+        self.tcp_keepalive = Some(interval);
+        self
+    }
+
+    // trusts an additional root CA, e.g. for a gateway behind an internal CA
+    pub fn add_root_certificate(mut self, cert: Certificate) -> Self {
+        self.root_certificates.push(cert);
+        self
+    }
+
+    pub fn add_root_certificate_pem(self, pem: &[u8]) -> Result<Self, ASError> {
+        Ok(self.add_root_certificate(Certificate::from_pem(pem)?))
+    }
+
+    pub fn add_root_certificate_der(self, der: &[u8]) -> Result<Self, ASError> {
+        Ok(self.add_root_certificate(Certificate::from_der(der)?))
+    }
+
+    // DANGEROUS: disables certificate validation, only meant for local development
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    // routes the gateway requests through `proxy`, e.g.
+    // `Proxy::all("http://proxy.internal:3128")`. Can be called several times,
+    // the first proxy matching a request is used. Everpay requests go through
+    // the client given to `EverpayClient`.
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxies.push(proxy);
+        self
+    }
+
+    // tags added to every uploaded item, tags given to a call take precedence
+    pub fn default_tags(mut self, tags: HashMap<String, String>) -> Self {
+        self.default_tags = tags;
+        self
+    }
+
+    // adds a `Unix-Time` tag with the current timestamp to every uploaded item
+    pub fn unix_time_tag(mut self, enabled: bool) -> Self {
+        self.unix_time_tag = enabled;
+        self
+    }
+
+    // adds a `Content-SHA256` tag with the hash of the data to every uploaded
+    // item, see `tags::content_sha256_tag`
+    pub fn content_sha256_tag(mut self, enabled: bool) -> Self {
+        self.content_sha256_tag = enabled;
+        self
+    }
+
+    // Failed requests return `ASError::WithBodies`, holding the request and
+    // response bodies (up to `DEBUG_BODY_LIMIT` bytes each, with the api key
+    // masked) around the usual error, match on `ASError::root` to reach it.
+    // Meant for debugging, as items and their data end up in errors and logs.
+    pub fn capture_debug_bodies(mut self, enabled: bool) -> Self {
+        self.capture_debug_bodies = enabled;
+        self
+    }
+
+    // currency used by the calls given an empty currency, e.g. `""` instead of
+    // `"usdc"` in `send_and_pay`. A non empty currency still overrides it.
+    pub fn default_currency(mut self, currency: &str) -> Self {
+        self.default_currency = Some(currency.to_string());
+        self
+    }
+
+    // largest item submitted to the gateway, bigger ones fail with
+    // `ItemTooLarge` before being sent. No limit is checked by default.
+    pub fn max_item_size(mut self, max: u64) -> Self {
+        self.max_item_size = Some(max);
+        self
+    }
+
+    // Signs the items built by the client (`bundle_and_submit`, `send_and_pay`...)
+    // with `signer` instead of the arweave key, e.g. the everpay signer so an
+    // ethereum account owns the items it pays for (ANS-104 signature type 3).
+    // No `Content-Type` is guessed for these items, set it in the tags.
+    pub fn item_signer(mut self, signer: Arc<dyn Signer + Send + Sync>) -> Self {
+        self.item_signer = Some(signer);
+        self
+    }
+
+    // gives the items built by the client a random anchor, so uploading the
+    // same data and tags twice (e.g. heartbeat records) gives two item ids
+    // even with a deterministic `item_signer`
+    pub fn random_anchor(mut self, enabled: bool) -> Self {
+        self.random_anchor = enabled;
+        self
+    }
+
+    // Number of times `bundle_and_submit` resubmits an item after a timeout or
+    // a failed connection, waiting longer between each. The item is signed once
+    // so every attempt sends the same item id. Defaults to 0.
+    pub fn submit_retries(mut self, retries: u32) -> Self {
+        self.submit_retries = retries;
+        self
+    }
+
+    // Most a payment in `currency` may cost, in human readable units (e.g. 0.5
+    // for 0.5 USDC). Paying more fails with `FeeExceedsLimit` before anything
+    // is transferred. Setting it again for a currency replaces the limit.
+    pub fn max_fee(mut self, limit: Decimal, currency: &str) -> Self {
+        self.max_fees.insert(currency.to_lowercase(), limit);
+        self
+    }
+
+    // api key sent with the submissions given an empty api key
+    pub fn api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+
+    // sends a sha-256 `Content-Digest` header with uploaded items and native
+    // data, gateways checking it reject uploads corrupted on the way
+    pub fn content_digest(mut self, enabled: bool) -> Self {
+        self.content_digest = enabled;
+        self
+    }
+
+    // maximum size in bytes of the data and metadata read from the gateway,
+    // bigger responses fail with `ResponseTooLarge`
+    pub fn max_response_size(mut self, max: usize) -> Self {
+        self.max_response_size = Some(max);
+        self
+    }
+
+    pub fn idempotency_cache(mut self, cache: IdempotencyCache) -> Self {
+        self.idempotency = Some(cache);
+        self
+    }
+
+    pub fn build(self) -> Result<ASClient, ASError> {
+        let mut cb = Client::builder();
+        if let Some(max) = self.pool_max_idle_per_host {
+            cb = cb.pool_max_idle_per_host(max);
+        }
+        if let Some(timeout) = self.pool_idle_timeout {
+            cb = cb.pool_idle_timeout(timeout);
+        }
+        if let Some(interval) = self.tcp_keepalive {
+            cb = cb.tcp_keepalive(interval);
+        }
+        for cert in self.root_certificates {
+            cb = cb.add_root_certificate(cert);
+        }
+        cb = cb.danger_accept_invalid_certs(self.accept_invalid_certs);
+        for proxy in self.proxies {
+            cb = cb.proxy(proxy);
+        }
+
+        let url = match self.url {
+            Some(url) => url,
+            None => parse_url(DEFAULT_ARSEEDING_URL)?,
+        };
+        // without arweave or everpay the client can still read
+        let mut c = ASClient::read_only(url, cb.build()?);
+        if let Some(arweave) = self.arweave {
+            c.arweave_url = arweave.base_url.clone();
+            c.arweave = Some(arweave);
+        }
+        c.everpay = self.everpay;
+        c.idempotency = self.idempotency;
+        c.default_tags = self.default_tags;
+        c.unix_time_tag = self.unix_time_tag;
+        c.content_sha256_tag = self.content_sha256_tag;
+        c.capture_debug_bodies = self.capture_debug_bodies;
+        c.max_response_size = self.max_response_size;
+        c.gateways = self.gateways;
+        c.default_currency = self.default_currency;
+        c.content_digest = self.content_digest;
+        c.api_key = self.api_key;
+        c.max_fees = self.max_fees;
+        c.submit_retries = self.submit_retries;
+        c.item_signer = self.item_signer;
+        c.random_anchor = self.random_anchor;
+        c.max_item_size = self.max_item_size;
+
+        Ok(c)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use crate::everpay::LocalEthSigner;
+    use crate::everpay_types::{Signer, TokenInfo, DEFAULT_EVERPAY_URL};
+    use std::path::PathBuf;
+    use std::str::FromStr;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::Instant;
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::mock_server::{mock_signer, route_server, MockRequest};
+
+    // everpay's `/info` with an empty token list, any other request is left
+    // hanging
+    async fn mock_server() -> Url {
+        let info = serde_json::to_string(&TokenInfo::default()).unwrap();
+        route_server(vec![("GET /info", 200, info)], true).await.0
+    }
+
+    // builder of a client on `url`, with everpay on `url` too paying with
+    // `signer` and the test arweave key
+    async fn client_on(url: &Url, signer: Arc<dyn Signer + Send + Sync>) -> ASClientBuilder {
+        let everpay = Everpay::new(EverpayClient::new(Client::new(), url.clone()), signer)
+            .await
+            .unwrap();
+        let arweave = arweave_from_keypair_bytes(
+            crate::test_vectors::ARWEAVE_KEYFILE,
+            parse_url(DEFAULT_ARWEAVE_URL).unwrap(),
+        )
+        .unwrap();
+        ASClient::builder()
+            .url(url.clone())
+            .arweave(arweave)
+            .everpay(everpay)
+    }
+
+    // `client_on` a `mock_server` with `mock_signer`
+    async fn mock_client() -> ASClientBuilder {
+        client_on(&mock_server().await, Arc::new(mock_signer())).await
+    }
+
+    #[tokio::test]
+    async fn it_uses_default_currency() {
+        let c = mock_client()
+            .await
+            .default_currency("usdc")
+            .build()
+            .unwrap();
+
+        assert_eq!(c.currency_or_default(""), "usdc");
+        assert_eq!(c.currency_or_default("ar"), "ar");
+    }
+
+    #[tokio::test]
+    async fn it_measures_item_size() {
+        let c = mock_client().await.build().unwrap();
+
+        let mut tags = HashMap::new();
+        tags.insert("hello".to_string(), "there".to_string());
+        let data = vec![7u8; 1000];
+
+        let item = c.signed_item(data.clone(), &tags).await.unwrap();
+        assert_eq!(c.item_size(&data, &tags).unwrap(), item.len());
+
+        let meta = crate::data_item::parse_data_item(&item).unwrap();
+        assert_eq!(meta.data, Base64(data).to_string());
+        assert!(meta.tags.iter().any(|t| t.name == "hello"));
+        assert!(meta
+            .tags
+            .iter()
+            .any(|t| t.name == tags::CONTENT_TYPE && t.value == tags::CONTENT_TYPE_OCTET_STREAM));
+    }
+
+    #[tokio::test]
+    async fn it_signs_items_with_item_signer() {
+        let signer =
+            LocalEthSigner::from_private_key(crate::test_vectors::ETH_PRIVATE_KEY).unwrap();
+        let c = ASClient::builder()
+            .url(parse_url(DEFAULT_ARSEEDING_URL).unwrap())
+            .item_signer(Arc::new(signer))
+            .build()
+            .unwrap();
+
+        let mut tags = HashMap::new();
+        tags.insert("hello".to_string(), "there".to_string());
+        let data = vec![7u8; 1000];
+
+        let item = c.signed_item(data.clone(), &tags).await.unwrap();
+        assert_eq!(c.item_size(&data, &tags).unwrap(), item.len());
+
+        let meta = crate::data_item::parse_data_item(&item).unwrap();
+        assert_eq!(
+            meta.signature_type,
+            crate::data_item::SIG_TYPE_ETHEREUM as i64
+        );
+        assert_eq!(meta.data, Base64(data).to_string());
+        assert!(meta.tags.iter().any(|t| t.name == "hello"));
+
+        // orders are looked up under the item signer's address
+        assert_eq!(
+            c.item_signer_address().unwrap(),
+            crate::test_vectors::ETH_ADDRESS
+        );
+        let read_only =
+            ASClient::read_only(parse_url(DEFAULT_ARSEEDING_URL).unwrap(), Client::new());
+        assert!(matches!(
+            read_only.item_signer_address(),
+            Err(ASError::NoKeypair { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_checks_pending_payments_before_paying_again() {
+        let bundler = "0x4002ed1a1410af1b4930cf6c479ae373debd6223";
+        let info = TokenInfo {
+            token_list: vec![TokenList {
+                tag: "ethereum-usdc-0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48".to_string(),
+                symbol: "USDC".to_string(),
+                decimals: 6,
+                transfer_fee: "0".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let fee = FeeRes {
+            currency: "USDC".to_string(),
+            decimals: 6,
+            final_fee: "10".to_string(),
+        };
+        let order = ItemSubmissionRes {
+            item_id: "paid-item".to_string(),
+            bundler: bundler.to_string(),
+            currency: "USDC".to_string(),
+            decimals: 6,
+            fee: "10".to_string(),
+            payment_expired_time: i64::MAX / 2,
+            ..Default::default()
+        };
+        let paid = OrderRes {
+            id: 1,
+            item_id: "paid-item".to_string(),
+            payment_status: "paid".to_string(),
+            ..Default::default()
+        };
+        // the client's gateway and everpay, the items go to `node`. Both the
+        // bundler and the decimals are checked against the node.
+        let (url, log) = route_server(
+            vec![
+                ("GET /info", 200, serde_json::to_string(&info).unwrap()),
+                (
+                    "GET /bundle/fee/0/usdc",
+                    200,
+                    serde_json::to_string(&FeeRes {
+                        decimals: 18,
+                        ..fee.clone()
+                    })
+                    .unwrap(),
+                ),
+                (
+                    "GET /bundle/bundler",
+                    200,
+                    json!({ "bundler": "0x0000000000000000000000000000000000000001" }).to_string(),
+                ),
+                // everpay took the transfer but the answer didn't make it back
+                ("POST /tx", 502, String::from("bad gateway")),
+            ],
+            false,
+        )
+        .await;
+        let orders = format!("GET /bundle/orders/{}", crate::test_vectors::ETH_ADDRESS);
+        let (node, node_log) = route_server(
+            vec![
+                (
+                    "GET /bundle/fee/0/usdc",
+                    200,
+                    serde_json::to_string(&fee).unwrap(),
+                ),
+                (
+                    "POST /bundle/tx/usdc",
+                    200,
+                    serde_json::to_string(&order).unwrap(),
+                ),
+                (
+                    "GET /bundle/bundler",
+                    200,
+                    json!({ "bundler": bundler }).to_string(),
+                ),
+                (&orders, 200, serde_json::to_string(&vec![paid]).unwrap()),
+            ],
+            false,
+        )
+        .await;
+
+        let signer = || {
+            Arc::new(
+                LocalEthSigner::from_private_key(crate::test_vectors::ETH_PRIVATE_KEY).unwrap(),
+            )
+        };
+        let cache = IdempotencyCache::new();
+        let c = client_on(&url, signer())
+            .await
+            .item_signer(signer())
+            .idempotency_cache(cache.clone())
+            .build()
+            .unwrap();
+        *c.gateway_health.lock().unwrap() = vec![GatewayHealth {
+            url: node,
+            healthy: true,
+            latency: None,
+        }];
+
+        let tags = HashMap::new();
+        let data = b"pay me once".to_vec();
+        assert!(c
+            .send_and_pay("usdc", &tags, data.clone(), "")
+            .await
+            .is_err());
+        assert_eq!(cache.pending_entries().len(), 1);
+
+        let item_id = c.send_and_pay("usdc", &tags, data, "").await.unwrap();
+        assert_eq!(item_id, "paid-item");
+        assert!(cache.pending_entries().is_empty());
+
+        // paid once, after checking the bundler and decimals of the node
+        let count = |log: &Mutex<Vec<MockRequest>>, line: &str| {
+            log.lock()
+                .unwrap()
+                .iter()
+                .filter(|r| r.line == line)
+                .count()
+        };
+        assert_eq!(count(&log, "POST /tx"), 1);
+        assert_eq!(count(&node_log, "POST /bundle/tx/usdc"), 1);
+        assert_eq!(count(&node_log, &orders), 1);
+        assert_eq!(count(&log, "GET /bundle/fee/0/usdc"), 0);
+    }
+
+    #[tokio::test]
+    async fn it_checks_item_ownership() {
+        let signer =
+            LocalEthSigner::from_private_key(crate::test_vectors::ETH_PRIVATE_KEY).unwrap();
+        let owner = Base64(signer.item_owner().await.unwrap()).to_string();
+
+        let meta = |owner: String| {
+            serde_json::to_string(&ItemMetaRes {
+                owner,
+                ..Default::default()
+            })
+            .unwrap()
+        };
+        let (url, _) = route_server(
+            vec![
+                ("GET /bundle/tx/mine", 200, meta(owner)),
+                ("GET /bundle/tx/native", 200, meta(String::from("bundler"))),
+            ],
+            false,
+        )
+        .await;
+
+        let c = ASClient::builder()
+            .url(url)
+            .item_signer(Arc::new(signer))
+            .build()
+            .unwrap();
+        assert!(c.is_own_item("mine").await.unwrap());
+        assert!(!c.is_own_item("native").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn it_rejects_items_over_max_size() {
+        let c = mock_client().await.max_item_size(10).build().unwrap();
+
+        let res = c.submit_item(vec![0u8; 11], "", "", None).await;
+        assert!(matches!(
+            res,
+            Err(ASError::ItemTooLarge { size: 11, max: 10 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_reports_rejected_api_keys() {
+        let (url, _) = route_server(
+            vec![
+                (
+                    "POST /bundle/tx/usdc",
+                    401,
+                    String::from(r#"{"error":"denied"}"#),
+                ),
+                (
+                    "GET /bundle/bundler",
+                    403,
+                    String::from(r#"{"error":"denied"}"#),
+                ),
+            ],
+            false,
+        )
+        .await;
+        let c = ASClient::builder().url(url).build().unwrap();
+
+        let res = c.submit_item(b"item".to_vec(), "usdc", "key", None).await;
+        assert!(matches!(res, Err(ASError::Unauthorized { e }) if e == "denied"));
+        // no key was sent, or it isn't a submission
+        let res = c.submit_item(b"item".to_vec(), "usdc", "", None).await;
+        assert!(matches!(res, Err(ASError::APIError { .. })));
+        assert!(matches!(
+            c.get_bundler().await,
+            Err(ASError::APIError { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_captures_debug_bodies() {
+        let (url, log) = route_server(
+            vec![(
+                "POST /bundle/data",
+                400,
+                String::from(r#"{"error":"err_invalid_signature sekret"}"#),
+            )],
+            false,
+        )
+        .await;
+
+        let data = b"the key is sekret".to_vec();
+        let c = ASClient::builder()
+            .url(url.clone())
+            .api_key("sekret")
+            .build()
+            .unwrap();
+        let res = c
+            .submit_native_data(data.clone(), "text/plain", None, &HashMap::new(), "")
+            .await;
+        assert!(matches!(res, Err(ASError::APIError { .. })));
+
+        let c = ASClient::builder()
+            .url(url)
+            .api_key("sekret")
+            .capture_debug_bodies(true)
+            .build()
+            .unwrap();
+        let res = c
+            .submit_native_data(data, "text/plain", None, &HashMap::new(), "")
+            .await;
+        assert!(matches!(
+            res.as_ref().map_err(|e| e.root()),
+            Err(ASError::APIError { .. })
+        ));
+        match res {
+            Err(ASError::WithBodies {
+                e,
+                request,
+                response,
+            }) => {
+                assert!(matches!(*e, ASError::APIError { .. }));
+                assert_eq!(request.unwrap(), "the key is [redacted]");
+                assert_eq!(response, r#"{"error":"err_invalid_signature [redacted]"}"#);
             }
+            r => panic!("expected WithBodies, got {:?}", r),
         }
+        assert!(log
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|r| r.body == b"the key is sekret"));
+
+        let long = vec![b'a'; DEBUG_BODY_LIMIT + 1];
+        let s = c.debug_body(&long);
+        assert!(s.starts_with(&"a".repeat(DEBUG_BODY_LIMIT)));
+        assert!(s.ends_with(&format!("... ({} bytes)", DEBUG_BODY_LIMIT + 1)));
+    }
+
+    #[tokio::test]
+    async fn it_submits_items_from_body() {
+        let submission = serde_json::to_string(&ItemSubmissionRes {
+            item_id: "from-body".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        let (url, log) = route_server(vec![("POST /bundle/tx/usdc", 200, submission)], false).await;
+
+        let c = ASClient::builder()
+            .url(url)
+            .max_item_size(1024)
+            .build()
+            .unwrap();
+        let chunks: Vec<Result<Bytes, std::io::Error>> = vec![
+            Ok(Bytes::from_static(b"signed")),
+            Ok(Bytes::from_static(b" item")),
+        ];
+        let body = Body::wrap_stream(stream::iter(chunks));
+
+        let res = c
+            .submit_item_from_body(body, 11, "usdc", "", None)
+            .await
+            .unwrap();
+        assert_eq!(res.item_id, "from-body");
+
+        let log = log.lock().unwrap();
+        assert!(log[0].headers.contains("content-length: 11\r\n"));
+        assert_eq!(log[0].body, b"signed item");
+    }
+
+    #[tokio::test]
+    async fn it_builds_read_only_clients() {
+        let url = parse_url(DEFAULT_ARSEEDING_URL).unwrap();
+        let c = ASClient::read_only(url.clone(), Client::new());
+        assert!(matches!(
+            c.item_size(b"test", &HashMap::new()),
+            Err(ASError::NoKeypair { .. })
+        ));
+        assert!(matches!(
+            c.send_and_pay("usdc", &HashMap::new(), b"test".to_vec(), "")
+                .await,
+            Err(ASError::ArgumentError { .. })
+        ));
+
+        let c = ASClient::builder().url(url).build().unwrap();
+        assert!(c.arweave.is_none() && c.everpay.is_none());
+        assert_eq!(c.arweave_url.as_str(), "https://arweave.net/");
+    }
+
+    #[tokio::test]
+    async fn it_waits_for_in_flight_work_on_shutdown() {
+        let c = ASClient::read_only(parse_url(DEFAULT_ARSEEDING_URL).unwrap(), Client::new());
+
+        let first = c.in_flight().unwrap();
+        let second = c.in_flight().unwrap();
+        let shutdown = c.shutdown();
+        tokio::pin!(shutdown);
+
+        let pending = tokio::time::timeout(Duration::from_millis(50), &mut shutdown).await;
+        assert!(pending.is_err());
+        drop(first);
+        let pending = tokio::time::timeout(Duration::from_millis(50), &mut shutdown).await;
+        assert!(pending.is_err());
+        // refused without being counted
+        assert!(matches!(c.in_flight(), Err(ASError::ShutDown)));
+        drop(second);
+        tokio::time::timeout(Duration::from_secs(1), shutdown)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_rejects_submissions_after_shutdown() {
+        let c = mock_client().await.build().unwrap();
+        c.shutdown().await;
+
+        let res = c
+            .send_and_pay("usdc", &HashMap::new(), b"too late".to_vec(), "")
+            .await;
+        assert!(matches!(res, Err(ASError::ShutDown)));
+        let res = c
+            .submit_native_data(
+                b"too late".to_vec(),
+                "text/plain",
+                None,
+                &HashMap::new(),
+                "",
+            )
+            .await;
+        assert!(matches!(res, Err(ASError::ShutDown)));
     }
 
-    pub async fn submit_native_data(
-        &self,
-        data: Vec<u8>,
-        content_type: &str,
-        tags: &HashMap<String, String>,
-        api_key: &str,
-    ) -> Result<SubmitNativeRes, ASError> {
-        let mut req = self
-            .client
-            .post(format!("{}{}", self.url, "bundle/data"))
-            .header("Content-Type", content_type)
-            .query(&["Content-Type", content_type])
-            .query(tags)
-            .body(data);
+    #[tokio::test]
+    async fn it_rejects_invalid_env() {
+        let vars = |vars: Vec<(&'static str, &'static str)>| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(k, _)| *k == name)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+
+        let err = |r: Result<ASClient, ASError>| match r {
+            Err(ASError::ArgumentError { arg }) => arg,
+            _ => panic!("expected an argument error"),
+        };
+
+        let e = err(ASClient::from_vars(vars(vec![])).await);
+        assert!(e.contains("ARWEAVE_KEY_JSON or ARWEAVE_KEYFILE"));
+
+        let e = err(ASClient::from_vars(vars(vec![("ARSEEDING_URL", "not a url")])).await);
+        assert!(e.contains("ARSEEDING_URL"));
 
-        if api_key.len() > 0 {
-            req = req.header("X-API-KEY", api_key);
-        }
+        let e = err(ASClient::from_vars(vars(vec![("ARWEAVE_KEYFILE", "./missing.json")])).await);
+        assert!(e.contains("ARWEAVE_KEYFILE"));
 
-        let res = req.send().await?;
+        let e = err(ASClient::from_vars(vars(vec![("ARWEAVE_KEY_JSON", "{}")])).await);
+        assert!(e.contains("JWK"));
+    }
 
-        match res.status() {
-            StatusCode::OK => return Ok(res.json::<SubmitNativeRes>().await?),
-            _ => {
-                return Err(ASError::APIError {
-                    e: res.json::<APIErrorRes>().await?.error,
-                })
-            }
+    #[tokio::test]
+    async fn it_cancels_uploads_on_drop() {
+        let c = mock_client().await.build().unwrap();
+
+        let total = 32 * 1024 * 1024;
+        let sent = Arc::new(AtomicU64::new(0));
+        let progress = sent.clone();
+        let upload = c.submit_item_with_progress(vec![0u8; total], "", "", move |n, _| {
+            progress.store(n, Ordering::SeqCst)
+        });
+
+        // the gateway never answers, the upload is cancelled by dropping it
+        let res = tokio::time::timeout(Duration::from_millis(500), upload).await;
+        assert!(res.is_err());
+        assert!(sent.load(Ordering::SeqCst) < total as u64);
+
+        // the body stream, and the callback with it, is released once the
+        // connection is torn down
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Arc::strong_count(&sent) > 1 {
+            assert!(Instant::now() < deadline, "upload body leaked");
+            tokio::time::sleep(Duration::from_millis(10)).await;
         }
     }
 
-    pub async fn get_bundle_fee(&self, size: &str, currency: &str) -> Result<FeeRes, ASError> {
-        let res = self
-            .client
-            .get(format!("{}bundle/fee/{}/{}", self.url, size, currency))
-            .send()
-            .await?;
+    #[test]
+    fn it_converts_decimals() {
+        assert_eq!(convert_decimals(12, 6, 6), Some(12));
+        assert_eq!(convert_decimals(12, 6, 8), Some(1200));
+        assert_eq!(convert_decimals(1200, 8, 6), Some(12));
+        assert_eq!(convert_decimals(1201, 8, 6), None);
+        assert_eq!(convert_decimals(u64::MAX, 0, 1), None);
+        assert_eq!(convert_decimals(1, 0, 30), None);
+    }
 
-        match res.status() {
-            StatusCode::OK => return Ok(res.json::<FeeRes>().await?),
-            _ => {
-                return Err(ASError::APIError {
-                    e: res.json::<APIErrorRes>().await?.error,
-                })
-            }
-        }
+    #[test]
+    fn it_batches_payments() {
+        let orders: Vec<ItemSubmissionRes> = (0..5)
+            .map(|i| ItemSubmissionRes {
+                item_id: format!("{:043}", i),
+                ..Default::default()
+            })
+            .collect();
+        let data_len = |batch: &[ItemSubmissionRes]| {
+            serde_json::to_string(&PayTxData {
+                app_name: String::from("arseeding"),
+                action: String::from("payment"),
+                item_ids: batch.iter().map(|o| o.item_id.clone()).collect(),
+            })
+            .unwrap()
+            .len()
+        };
+
+        let max = data_len(&orders[..2]);
+        let batches = payment_batches(&orders, max);
+        assert_eq!(
+            batches.iter().map(|b| b.len()).collect::<Vec<_>>(),
+            vec![2, 2, 1]
+        );
+        assert!(batches.iter().all(|b| data_len(b) <= max));
+
+        assert_eq!(
+            payment_batches(&orders, crate::everpay_types::MAX_TX_DATA_LEN).len(),
+            1
+        );
+        assert_eq!(payment_batches(&orders, 1).len(), 5);
+        assert!(payment_batches(&[], 1).is_empty());
     }
 
-    pub async fn get_bundler_orders(
-        &self,
-        signer: &str,
-        cursor: &str,
-    ) -> Result<Vec<OrderRes>, ASError> {
-        let mut req = self
-            .client
-            .get(format!("{}bundle/orders/{}", self.url, signer));
+    #[test]
+    fn it_splits_submissions() {
+        let sizes = [40, 30, 30, 90, 10, 10, 10];
+        assert_eq!(submit_batches(&sizes, Some(100), 10), vec![3, 2, 2]);
+        assert_eq!(submit_batches(&sizes, Some(100), 2), vec![2, 1, 2, 2]);
+        assert_eq!(submit_batches(&sizes, None, 3), vec![3, 3, 1]);
+        // larger than the limit on its own
+        assert_eq!(submit_batches(&[150, 10], Some(100), 10), vec![1, 1]);
+        assert!(submit_batches(&[], Some(100), 10).is_empty());
+    }
 
-        if cursor.len() > 0 {
-            req = req.query(&["cursor", cursor]);
-        }
+    #[test]
+    fn it_computes_upload_cost() {
+        let token = TokenList {
+            symbol: "USDC".to_string(),
+            decimals: 6,
+            transfer_fee: "10".to_string(),
+            ..Default::default()
+        };
+        let fee = |final_fee: &str, decimals| FeeRes {
+            currency: "USDC".to_string(),
+            decimals,
+            final_fee: final_fee.to_string(),
+        };
+
+        assert_eq!(upload_cost(&fee("1000", 6), &token).unwrap(), 1010);
+        assert_eq!(upload_cost(&fee("100000", 8), &token).unwrap(), 1010);
+        assert!(matches!(
+            upload_cost(&fee("1", 8), &token),
+            Err(ASError::DecimalMismatch { .. })
+        ));
+        assert!(upload_cost(&fee("abc", 6), &token).is_err());
+    }
 
-        let res = req.send().await?;
+    #[test]
+    fn it_ranks_gateways() {
+        let gateway = |url: &str, latency: Option<u64>| GatewayHealth {
+            url: parse_url(url).unwrap(),
+            healthy: latency.is_some(),
+            latency: latency.map(Duration::from_millis),
+        };
+
+        let ranked = rank_gateways(vec![
+            gateway("https://a.dev", None),
+            gateway("https://b.dev", Some(300)),
+            gateway("https://c.dev", Some(20)),
+            gateway("https://d.dev", None),
+        ]);
+        let urls: Vec<&str> = ranked.iter().map(|g| g.url.as_str()).collect();
+
+        assert_eq!(
+            urls,
+            vec![
+                "https://c.dev/",
+                "https://b.dev/",
+                "https://a.dev/",
+                "https://d.dev/"
+            ]
+        );
+    }
 
-        match res.status() {
-            StatusCode::OK => return Ok(res.json::<Vec<OrderRes>>().await?),
-            _ => {
-                return Err(ASError::APIError {
-                    e: res.json::<APIErrorRes>().await?.error,
-                })
-            }
-        }
+    #[test]
+    fn it_checks_fee_limit() {
+        let limit = Decimal::from_str("0.5").unwrap();
+
+        assert!(check_fee_limit("USDC", 500_000, 6, limit).is_ok());
+        assert!(check_fee_limit("USDC", 0, 6, limit).is_ok());
+        assert!(matches!(
+            check_fee_limit("USDC", 500_001, 6, limit),
+            Err(ASError::FeeExceedsLimit { quoted, .. }) if quoted == Decimal::from_str("0.500001").unwrap()
+        ));
+        assert!(check_fee_limit("AR", u64::MAX as u128 * 2, 12, limit).is_err());
     }
 
-    pub async fn get_item_meta(&self, item_id: &str) -> Result<ItemMetaRes, ASError> {
-        let res = self
-            .client
-            .get(format!("{}bundle/tx/{}", self.url, item_id))
-            .send()
-            .await?;
+    #[test]
+    fn it_overrides_fees() {
+        let order = ItemSubmissionRes {
+            item_id: "item".to_string(),
+            currency: "USDC".to_string(),
+            fee: "100".to_string(),
+            ..Default::default()
+        };
+
+        let tipped = with_fee_override(&order, 150).unwrap();
+        assert_eq!(tipped.fee, "150");
+        assert_eq!(tipped.item_id, "item");
+        assert_eq!(with_fee_override(&order, 100).unwrap().fee, "100");
+        assert!(matches!(
+            with_fee_override(&order, 99),
+            Err(ASError::FeeTooLow {
+                quoted: 100,
+                fee: 99,
+                ..
+            })
+        ));
+    }
 
-        match res.status() {
-            StatusCode::OK => return Ok(res.json::<ItemMetaRes>().await?),
-            _ => {
-                return Err(ASError::APIError {
-                    e: res.json::<APIErrorRes>().await?.error,
-                })
-            }
-        }
+    #[test]
+    fn it_verifies_gateway_everpay_match() {
+        let advertised = BundlerRes {
+            bundler: "0x4002ED1a1410aF1b4930cF6c479ae373dEbD6223".to_string(),
+        };
+        let submission = |bundler: &str| ItemSubmissionRes {
+            bundler: bundler.to_string(),
+            ..Default::default()
+        };
+
+        assert!(verify_gateway_everpay_match(
+            &advertised,
+            &submission("0x4002ed1a1410af1b4930cf6c479ae373debd6223")
+        )
+        .is_ok());
+        assert!(matches!(
+            verify_gateway_everpay_match(
+                &advertised,
+                &submission("0x0000000000000000000000000000000000000001")
+            ),
+            Err(ASError::BundlerMismatch { advertised, .. }) if advertised.starts_with("0x4002")
+        ));
     }
 
-    pub async fn get_items_by_ar_id(&self, ar_id: &str) -> Result<Vec<String>, ASError> {
-        let res = self
-            .client
-            .get(format!("{}bundle/itemIds/{}", self.url, ar_id))
-            .send()
-            .await?;
+    #[test]
+    fn it_retries_transient_errors_only() {
+        assert!(is_transient(&ASError::Timeout {
+            e: "no answer".to_string()
+        }));
+        assert!(!is_transient(&ASError::api_error("err_invalid_signature")));
+        assert!(!is_transient(&ASError::ItemTooLarge { size: 2, max: 1 }));
+    }
 
-        match res.status() {
-            StatusCode::OK => return Ok(res.json::<Vec<String>>().await?),
-            _ => {
-                return Err(ASError::APIError {
-                    e: res.json::<APIErrorRes>().await?.error,
-                })
-            }
-        }
+    #[test]
+    fn it_computes_backlog() {
+        let order = |payment: &str, on_chain: &str, expected_block: i64| OrderRes {
+            payment_status: payment.to_string(),
+            on_chain_status: on_chain.to_string(),
+            expected_block,
+            ..Default::default()
+        };
+        let orders = vec![
+            order("paid", "waiting", 1005),
+            order("paid", "pending", 1002),
+            order("paid", "success", 1010),
+            order("unpaid", "waiting", 1020),
+            order("paid", "waiting", 990),
+        ];
+
+        let b = backlog(&orders, 1000);
+        assert_eq!(b.pending_items, 3);
+        assert_eq!(b.blocks_to_seed, 5);
+        assert_eq!(b.estimated_time_to_seed, Duration::from_secs(600));
+
+        assert_eq!(backlog(&[], 1000), BacklogInfo::default());
     }
-}
 
-#[cfg(test)]
-mod test {
+    #[test]
+    fn it_reconciles_decimals() {
+        let fee = FeeRes {
+            currency: "USDC".to_string(),
+            decimals: 6,
+            final_fee: "1000".to_string(),
+        };
+        let order = |currency: &str, decimals| ItemSubmissionRes {
+            currency: currency.to_string(),
+            decimals,
+            ..Default::default()
+        };
+
+        assert!(reconcile_decimals(&fee, &order("usdc", 6)).is_ok());
+        assert!(matches!(
+            reconcile_decimals(&fee, &order("USDC", 18)),
+            Err(ASError::FeeDecimalMismatch {
+                item_decimals: 18,
+                fee_decimals: 6,
+                ..
+            })
+        ));
+        assert!(matches!(
+            reconcile_decimals(&fee, &order("AR", 6)),
+            Err(ASError::ArgumentError { .. })
+        ));
+    }
 
-    use crate::{everpay::ArweaveSigner, everpay_client::EverpayClient, everpay_types::Signer};
-    use std::path::PathBuf;
-    use std::str::FromStr;
-    use std::sync::Arc;
+    #[test]
+    fn it_computes_content_digests() {
+        // RFC 9530 example
+        assert_eq!(
+            content_digest(br#"{"hello": "world"}"#),
+            "sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:"
+        );
+    }
 
-    use super::*;
+    #[test]
+    fn it_computes_idempotency_keys() {
+        let mut tags = HashMap::new();
+        tags.insert("a".to_string(), "b".to_string());
+        tags.insert("c".to_string(), "d".to_string());
+
+        let key = idempotency_key(b"data", &tags, "USDC");
+        assert_eq!(key, idempotency_key(b"data", &tags.clone(), "usdc"));
+        assert_ne!(key, idempotency_key(b"data", &tags, "ar"));
+        assert_ne!(key, idempotency_key(b"other", &tags, "usdc"));
+
+        let mut shifted = HashMap::new();
+        shifted.insert("ab".to_string(), "".to_string());
+        shifted.insert("c".to_string(), "d".to_string());
+        assert_ne!(key, idempotency_key(b"data", &shifted, "usdc"));
+
+        let cache = IdempotencyCache::new();
+        cache.insert(key.clone(), "item".to_string());
+        let restored = IdempotencyCache::from_entries(cache.entries());
+        assert_eq!(restored.get(&key), Some("item".to_string()));
+    }
 
     async fn init_default<'a>(signer: Arc<dyn Signer + Send + Sync>, arweave: Arweave) -> ASClient {
         let everpay = Everpay::new(EverpayClient::default(), signer)
             .await
             .unwrap();
-        ASClient::new(
-            Url::from_str(DEFAULT_ARSEEDING_URL).unwrap(),
+        ASClient::from_url_str(
+            DEFAULT_ARSEEDING_URL,
             reqwest::Client::new(),
             arweave,
             everpay,
         )
+        .unwrap()
     }
 
     #[tokio::test]
@@ -285,6 +3065,20 @@ mod test {
         println!("{:#?}", res);
     }
 
+    #[tokio::test]
+    #[ignore = "outbound_calls"]
+    async fn it_gets_ar_tx_data() {
+        let signer = Arc::new(ArweaveSigner::new(Arweave::default()));
+        let ar = Arweave::default();
+        let c = init_default(signer, ar).await;
+        let res = c
+            .get_ar_tx_data("-19XXEkalF_klxLLpknoTGAr6AnQMCgqzz-GjNn-oSE")
+            .await
+            .unwrap();
+
+        println!("{}", res.len());
+    }
+
     #[tokio::test]
     #[ignore = "outbound_calls"]
     async fn it_gets_bundlr() {
@@ -309,6 +3103,31 @@ mod test {
         println!("{:#?}", res);
     }
 
+    #[tokio::test]
+    #[ignore = "outbound_calls"]
+    async fn it_estimates_fees() {
+        let ar = Arweave::default();
+        let signer = Arc::new(ArweaveSigner::new(Arweave::default()));
+        let c = init_default(signer, ar).await;
+        let res = c.estimate_fees(&[1000, 300_000], "USDC").await.unwrap();
+
+        println!("{:#?}", res);
+    }
+
+    #[tokio::test]
+    #[ignore = "outbound_calls"]
+    async fn it_checks_affordability() {
+        let ar = Arweave::default();
+        let signer = Arc::new(ArweaveSigner::new(Arweave::default()));
+        let c = init_default(signer, ar).await;
+        let res = c
+            .can_afford("2NbYHgsuI8uQcuErDsgoRUCyj9X2wZ6PBN6WTz9xyu0", 1000, "USDC")
+            .await
+            .unwrap();
+
+        println!("{:#?}", res);
+    }
+
     #[tokio::test]
     #[ignore = "outbound_calls"]
     async fn it_fetches_orders() {
@@ -337,6 +3156,280 @@ mod test {
         println!("{:#?}", res);
     }
 
+    #[tokio::test]
+    #[ignore = "outbound_calls"]
+    async fn it_gets_data_range() {
+        let signer = Arc::new(ArweaveSigner::new(Arweave::default()));
+        let ar = Arweave::default();
+        let c = init_default(signer, ar).await;
+        let res = c
+            .get_data_range("BewjUEppPQ9pljVrjMxF7A2Kkz5ZJt_Q7tXRkQDm2VQ", 0, 9)
+            .await
+            .unwrap();
+
+        println!("{:?}", res);
+    }
+
+    #[tokio::test]
+    async fn it_sorts_bundler_orders() {
+        let (url, _) = route_server(
+            vec![(
+                "GET /bundle/orders/signer?cursor=5&sort=id&order=desc",
+                200,
+                String::from("[]"),
+            )],
+            false,
+        )
+        .await;
+
+        let c = ASClient::read_only(url, Client::new());
+        let orders = c
+            .get_bundler_orders_sorted("signer", "5", "id", Some(SortOrder::Desc))
+            .await
+            .unwrap();
+        assert!(orders.is_empty());
+    }
+
+    #[tokio::test]
+    async fn it_exports_orders() {
+        let orders = |ids: Vec<u64>| {
+            let orders: Vec<OrderRes> = ids
+                .into_iter()
+                .map(|id| OrderRes {
+                    id,
+                    fee: "10".to_string(),
+                    ..Default::default()
+                })
+                .collect();
+            serde_json::to_string(&orders).unwrap()
+        };
+        let (url, _) = route_server(
+            vec![
+                ("GET /bundle/orders/signer", 200, orders(vec![3, 2])),
+                ("GET /bundle/orders/signer?cursor=2", 200, orders(vec![1])),
+                ("GET /bundle/orders/signer?cursor=1", 200, orders(vec![])),
+            ],
+            false,
+        )
+        .await;
+
+        let c = ASClient::read_only(url, Client::new());
+        let mut out = vec![];
+        let count = c.export_orders("signer", &mut out).await.unwrap();
+        assert_eq!(count, 3);
+
+        let ids: Vec<u64> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str::<OrderRes>(l).unwrap().id)
+            .collect();
+        assert_eq!(ids, vec![3, 2, 1]);
+
+        let pages: Vec<Vec<OrderRes>> = c
+            .orders_paginated("signer".to_string())
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(
+            pages.iter().map(|p| p.len()).collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+
+        // stopping after the first page
+        let first: Vec<_> = c
+            .orders_paginated("signer".to_string())
+            .take(1)
+            .collect()
+            .await;
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].as_ref().unwrap()[0].id, 3);
+    }
+
+    #[tokio::test]
+    async fn it_reports_timeouts() {
+        let url = mock_server().await;
+        let client = Client::builder()
+            .timeout(Duration::from_millis(100))
+            .build()
+            .unwrap();
+        let c = ASClient::read_only(url, client);
+
+        assert!(matches!(
+            c.get_data("x").await,
+            Err(ASError::Timeout { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_reports_connection_failures() {
+        // nothing listens on the port once the listener is dropped
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let url = parse_url(&format!("http://{}", addr)).unwrap();
+        let c = ASClient::read_only(url, Client::new());
+        match c.get_data("x").await {
+            Err(ASError::ConnectionFailed { url, reason }) => {
+                assert!(url.contains(&addr.to_string()));
+                assert_eq!(reason, "the connection was refused");
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_checks_item_existence() {
+        let (url, _) = route_server(
+            vec![
+                ("HEAD /bundle/tx/seeded/data", 200, String::new()),
+                ("HEAD /bundle/tx/unknown/data", 404, String::new()),
+                ("HEAD /bundle/tx/other/data", 500, String::new()),
+            ],
+            false,
+        )
+        .await;
+
+        let c = ASClient::read_only(url, Client::new());
+        assert!(c.exists("seeded").await.unwrap());
+        assert!(!c.exists("unknown").await.unwrap());
+        assert!(matches!(
+            c.exists("other").await,
+            Err(ASError::APIError { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_gets_data_many() {
+        let (url, _) = route_server(
+            vec![
+                (
+                    "GET /bundle/tx/a/data",
+                    200,
+                    String::from("/bundle/tx/a/data"),
+                ),
+                (
+                    "GET /bundle/tx/b/data",
+                    200,
+                    String::from("/bundle/tx/b/data"),
+                ),
+            ],
+            false,
+        )
+        .await;
+
+        let c = ASClient::read_only(url, Client::new());
+        let ids = vec!["a".to_string(), "missing".to_string(), "b".to_string()];
+        let mut res: Vec<_> = c.get_data_many(ids).collect().await;
+        res.sort_by(|a, b| a.0.cmp(&b.0));
+
+        assert_eq!(res.len(), 3);
+        assert_eq!(res[0].0, "a");
+        assert_eq!(res[0].1.as_ref().unwrap(), b"/bundle/tx/a/data");
+        assert_eq!(res[1].1.as_ref().unwrap(), b"/bundle/tx/b/data");
+        assert_eq!(res[2].0, "missing");
+        assert!(matches!(res[2].1, Err(ASError::APIError { .. })));
+    }
+
+    #[tokio::test]
+    #[ignore = "outbound_calls"]
+    async fn it_checks_gateway_health() {
+        let signer = Arc::new(ArweaveSigner::new(Arweave::default()));
+        let everpay = Everpay::new(EverpayClient::default(), signer)
+            .await
+            .unwrap();
+        let c = ASClient::builder()
+            .arweave(Arweave::default())
+            .everpay(everpay)
+            .gateways(vec![parse_url("https://arseed.web3infura.io").unwrap()])
+            .build()
+            .unwrap();
+
+        let res = c.refresh_gateway_health().await;
+
+        println!("{:#?}", res);
+        println!("{}", c.submission_url());
+    }
+
+    #[tokio::test]
+    async fn it_routes_through_proxy() {
+        // proxied requests carry the absolute url
+        let (proxy, _) = route_server(
+            vec![(
+                "GET http://arseeding.invalid/bundle/bundler",
+                200,
+                String::from(r#"{"bundler":"proxied"}"#),
+            )],
+            false,
+        )
+        .await;
+
+        let c = ASClient::builder()
+            .url(parse_url("http://arseeding.invalid").unwrap())
+            .proxy(Proxy::http(proxy.as_str()).unwrap())
+            .build()
+            .unwrap();
+
+        assert_eq!(c.get_bundler().await.unwrap().bundler, "proxied");
+    }
+
+    #[tokio::test]
+    #[ignore = "outbound_calls"]
+    async fn it_builds_with_pool_settings() {
+        let signer = Arc::new(ArweaveSigner::new(Arweave::default()));
+        let everpay = Everpay::new(EverpayClient::default(), signer)
+            .await
+            .unwrap();
+
+        let c = ASClient::builder()
+            .arweave(Arweave::default())
+            .everpay(everpay)
+            .pool_max_idle_per_host(16)
+            .pool_idle_timeout(Duration::from_secs(90))
+            .tcp_keepalive(Duration::from_secs(30))
+            .build()
+            .unwrap();
+
+        println!("{:#?}", c.get_bundler().await.unwrap());
+    }
+
+    #[tokio::test]
+    #[ignore = "outbound_calls"]
+    async fn it_builds_from_keypair_bytes() {
+        let jwk = std::fs::read(
+            "./tests/fixtures/arweave-key-7eV1qae4qVNqsNChg3Scdi-DpOLJPCogct4ixoq1WNg.json",
+        )
+        .unwrap();
+
+        let c = ASClient::from_keypair_bytes(&jwk, DEFAULT_ARSEEDING_URL, DEFAULT_EVERPAY_URL)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            c.arweave()
+                .unwrap()
+                .crypto
+                .wallet_address()
+                .unwrap()
+                .to_string(),
+            "7eV1qae4qVNqsNChg3Scdi-DpOLJPCogct4ixoq1WNg"
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "outbound_calls"]
+    async fn it_gets_seeding_status() {
+        let signer = Arc::new(ArweaveSigner::new(Arweave::default()));
+        let ar = Arweave::default();
+        let c = init_default(signer, ar).await;
+        let res = c
+            .seeding_status("BewjUEppPQ9pljVrjMxF7A2Kkz5ZJt_Q7tXRkQDm2VQ")
+            .await
+            .unwrap();
+
+        println!("{:#?}", res);
+    }
+
     #[tokio::test]
     #[ignore = "outbound_calls"]
     async fn it_bundles_and_submits() {
@@ -375,10 +3468,40 @@ mod test {
         tags.insert("hello".to_string(), "there".to_string());
 
         let res = c
-            .bundle_and_submit("test".as_bytes().to_vec(), &tags, "usdc", "")
+            .bundle_and_submit("test".as_bytes().to_vec(), &tags, "usdc", "", None)
+            .await
+            .unwrap();
+
+        println!("{:#?}", res);
+    }
+
+    #[tokio::test]
+    #[ignore = "outbound_calls"]
+    async fn it_submits_items_stream() {
+        let jwk = std::fs::read(
+            "./tests/fixtures/arweave-key-7eV1qae4qVNqsNChg3Scdi-DpOLJPCogct4ixoq1WNg.json",
+        )
+        .unwrap();
+        let c = ASClient::from_keypair_bytes(&jwk, DEFAULT_ARSEEDING_URL, DEFAULT_EVERPAY_URL)
             .await
             .unwrap();
 
+        let items = stream::iter(0..3).map(|i| {
+            let item = c
+                .arweave()
+                .unwrap()
+                .create_data_item(format!("item {}", i).into_bytes(), vec![], true)
+                .unwrap();
+            c.arweave()
+                .unwrap()
+                .sign_data_item(item)
+                .unwrap()
+                .serialize()
+                .unwrap()
+        });
+
+        let res: Vec<_> = c.submit_items_stream(items, "usdc", "").collect().await;
+
         println!("{:#?}", res);
     }
 
@@ -426,4 +3549,40 @@ mod test {
 
         println!("{:#?}", res);
     }
+
+    #[tokio::test]
+    #[ignore = "outbound_calls"]
+    async fn it_submits_many_and_pays() {
+        let jwk = crate::test_vectors::ARWEAVE_KEYFILE;
+        let signer = Arc::new(ArweaveSigner::from_keypair_bytes(jwk).unwrap());
+        let arweave =
+            arweave_from_keypair_bytes(jwk, parse_url(DEFAULT_ARWEAVE_URL).unwrap()).unwrap();
+        let c = init_default(signer, arweave).await;
+
+        let files = (0..3)
+            .map(|i| {
+                let mut tags = HashMap::new();
+                tags.insert("index".to_string(), i.to_string());
+                (format!("test{}", i).into_bytes(), tags)
+            })
+            .collect();
+
+        let res = c.submit_many_and_pay(files, "ar", "").await.unwrap();
+
+        println!("{:#?}", res);
+    }
+
+    #[tokio::test]
+    #[ignore = "outbound_calls"]
+    async fn it_runs_self_test() {
+        let jwk = crate::test_vectors::ARWEAVE_KEYFILE;
+        let signer = Arc::new(ArweaveSigner::from_keypair_bytes(jwk).unwrap());
+        let arweave =
+            arweave_from_keypair_bytes(jwk, parse_url(DEFAULT_ARWEAVE_URL).unwrap()).unwrap();
+        let c = init_default(signer, arweave).await;
+
+        let res = c.self_test("ar", "").await;
+
+        println!("{:#?}", res);
+    }
 }
@@ -0,0 +1,461 @@
+use arloader::transaction::Base64;
+use ring::digest::{digest, SHA256, SHA384};
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::arseeding_types::{ASError, ItemMetaRes, Tag};
+use crate::everpay_types::Signer;
+
+// ANS-104 signature types
+pub const SIG_TYPE_ARWEAVE: u16 = 1;
+pub const SIG_TYPE_ED25519: u16 = 2;
+pub const SIG_TYPE_ETHEREUM: u16 = 3;
+pub const SIG_TYPE_SOLANA: u16 = 4;
+
+// bytes of an item's anchor, when it has one
+pub const ANCHOR_LEN: usize = 32;
+
+// (signature length, owner length) for a given ANS-104 signature type
+pub fn sig_config(signature_type: u16) -> Option<(usize, usize)> {
+    match signature_type {
+        SIG_TYPE_ARWEAVE => Some((512, 512)),
+        SIG_TYPE_ED25519 => Some((64, 32)),
+        SIG_TYPE_ETHEREUM => Some((65, 65)),
+        SIG_TYPE_SOLANA => Some((64, 32)),
+        _ => None,
+    }
+}
+
+// decodes a serialized ANS-104 data item so it can be inspected before submission
+pub fn parse_data_item(bytes: &[u8]) -> Result<ItemMetaRes, ASError> {
+    let mut r = Reader { bytes, pos: 0 };
+
+    let signature_type = u16::from_le_bytes([r.u8()?, r.u8()?]);
+    let (sig_len, owner_len) = sig_config(signature_type).ok_or(ASError::ArgumentError {
+        arg: format!("unknown signature type: {}", signature_type),
+    })?;
+
+    let signature = r.take(sig_len)?;
+    let owner = r.take(owner_len)?;
+    let target = r.optional(32)?;
+    let anchor = r.optional(32)?;
+
+    let tag_count = r.u64_le()?;
+    let tag_bytes_len = r.u64_le()?;
+    let tag_bytes = r.take(usize::try_from(tag_bytes_len).map_err(|_| invalid("tags length"))?)?;
+    let tags = decode_tags(tag_bytes)?;
+    if tags.len() as u64 != tag_count {
+        return Err(invalid("tags count"));
+    }
+
+    let data = &bytes[r.pos..];
+
+    Ok(ItemMetaRes {
+        signature_type: signature_type as i64,
+        signature: Base64(signature.to_vec()).to_string(),
+        owner: Base64(owner.to_vec()).to_string(),
+        target: Base64(target.to_vec()).to_string(),
+        anchor: Base64(anchor.to_vec()).to_string(),
+        tags,
+        data: Base64(data.to_vec()).to_string(),
+        id: Base64(digest(&SHA256, signature).as_ref().to_vec()).to_string(),
+    })
+}
+
+// `let meta: ItemMetaRes = bytes.try_into()?`, see `parse_data_item`
+impl TryFrom<&[u8]> for ItemMetaRes {
+    type Error = ASError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        parse_data_item(bytes)
+    }
+}
+
+// Signs `data` and `tags` into a serialized ANS-104 item, without target. The
+// signature type and owner come from `signer`, e.g. an ethereum key gives a
+// type 3 item owned by that key. `anchor` is empty or 32 bytes.
+pub async fn sign_data_item(
+    signer: &(dyn Signer + Send + Sync),
+    data: &[u8],
+    tags: &[(String, String)],
+    anchor: &[u8],
+) -> Result<Vec<u8>, ASError> {
+    if !anchor.is_empty() && anchor.len() != ANCHOR_LEN {
+        return Err(invalid("anchor length"));
+    }
+    let signature_type = item_signature_type(signer)?;
+    let owner = signer.item_owner().await?;
+    let tag_bytes = encode_tags(tags);
+
+    let message = deep_hash(&[
+        b"dataitem",
+        b"1",
+        signature_type.to_string().as_bytes(),
+        &owner,
+        &[],
+        anchor,
+        &tag_bytes,
+        data,
+    ]);
+    let signature = signer.sign_item(&message).await?;
+
+    let (sig_len, owner_len) =
+        sig_config(signature_type).ok_or_else(|| invalid("signature type"))?;
+    if signature.len() != sig_len {
+        return Err(invalid("signature length"));
+    }
+    if owner.len() != owner_len {
+        return Err(invalid("owner length"));
+    }
+
+    let mut b = Vec::with_capacity(data_item_len(
+        signature_type,
+        &tag_bytes,
+        data.len(),
+        !anchor.is_empty(),
+    ));
+    b.extend_from_slice(&signature_type.to_le_bytes());
+    b.extend_from_slice(&signature);
+    b.extend_from_slice(&owner);
+    // no target
+    b.push(0);
+    if anchor.is_empty() {
+        b.push(0);
+    } else {
+        b.push(1);
+        b.extend_from_slice(anchor);
+    }
+    b.extend_from_slice(&(tags.len() as u64).to_le_bytes());
+    b.extend_from_slice(&(tag_bytes.len() as u64).to_le_bytes());
+    b.extend_from_slice(&tag_bytes);
+    b.extend_from_slice(data);
+    Ok(b)
+}
+
+// size of the item `sign_data_item` builds, without signing it
+pub fn signed_data_item_len(
+    signer: &(dyn Signer + Send + Sync),
+    data_len: usize,
+    tags: &[(String, String)],
+    anchored: bool,
+) -> Result<usize, ASError> {
+    let signature_type = item_signature_type(signer)?;
+    Ok(data_item_len(
+        signature_type,
+        &encode_tags(tags),
+        data_len,
+        anchored,
+    ))
+}
+
+// Random anchor, so that items with the same data, tags and a deterministic
+// signature (ethereum, ed25519) still get distinct ids.
+pub fn random_anchor() -> Result<[u8; ANCHOR_LEN], ASError> {
+    let mut anchor = [0u8; ANCHOR_LEN];
+    SystemRandom::new()
+        .fill(&mut anchor)
+        .map_err(|_| ASError::ArgumentError {
+            arg: String::from("no randomness for the item anchor"),
+        })?;
+    Ok(anchor)
+}
+
+fn item_signature_type(signer: &(dyn Signer + Send + Sync)) -> Result<u16, ASError> {
+    signer
+        .signer_type()
+        .ans104_code()
+        .ok_or_else(|| ASError::ArgumentError {
+            arg: format!("{} signers can't sign data items", signer.signer_type()),
+        })
+}
+
+pub(crate) fn data_item_len(
+    signature_type: u16,
+    tag_bytes: &[u8],
+    data_len: usize,
+    anchored: bool,
+) -> usize {
+    let (sig_len, owner_len) = sig_config(signature_type).unwrap_or_default();
+    let anchor_len = if anchored { ANCHOR_LEN } else { 0 };
+    2 + sig_len + owner_len + 1 + 1 + anchor_len + 8 + 8 + tag_bytes.len() + data_len
+}
+
+// arweave deep hash of a list of blobs, the message items are signed over
+fn deep_hash(chunks: &[&[u8]]) -> Vec<u8> {
+    let sha384 = |b: &[u8]| digest(&SHA384, b).as_ref().to_vec();
+
+    let mut acc = sha384(format!("list{}", chunks.len()).as_bytes());
+    for chunk in chunks {
+        let tag = sha384(format!("blob{}", chunk.len()).as_bytes());
+        let blob = sha384(&[tag, sha384(chunk)].concat());
+        acc = sha384(&[acc, blob].concat());
+    }
+    acc
+}
+
+// tags as an avro array of { name: bytes, value: bytes } records, empty when
+// there are no tags
+pub(crate) fn encode_tags(tags: &[(String, String)]) -> Vec<u8> {
+    let mut out = vec![];
+    if tags.is_empty() {
+        return out;
+    }
+
+    encode_long(tags.len() as i64, &mut out);
+    for (name, value) in tags {
+        encode_long(name.len() as i64, &mut out);
+        out.extend_from_slice(name.as_bytes());
+        encode_long(value.len() as i64, &mut out);
+        out.extend_from_slice(value.as_bytes());
+    }
+    out.push(0);
+    out
+}
+
+// avro zigzag encoded long
+fn encode_long(n: i64, out: &mut Vec<u8>) {
+    let mut z = ((n << 1) ^ (n >> 63)) as u64;
+    while z >= 0x80 {
+        out.push((z as u8 & 0x7f) | 0x80);
+        z >>= 7;
+    }
+    out.push(z as u8);
+}
+
+fn invalid(field: &str) -> ASError {
+    ASError::ArgumentError {
+        arg: format!("invalid data item: {}", field),
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], ASError> {
+        let end = self.pos.checked_add(n).ok_or_else(|| invalid("length"))?;
+        let b = self
+            .bytes
+            .get(self.pos..end)
+            .ok_or_else(|| invalid("truncated"))?;
+        self.pos = end;
+        Ok(b)
+    }
+
+    fn u8(&mut self) -> Result<u8, ASError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u64_le(&mut self) -> Result<u64, ASError> {
+        let mut b = [0u8; 8];
+        b.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(b))
+    }
+
+    // presence byte followed by a fixed size field
+    fn optional(&mut self, n: usize) -> Result<&'a [u8], ASError> {
+        match self.u8()? {
+            0 => Ok(&[]),
+            1 => self.take(n),
+            _ => Err(invalid("presence byte")),
+        }
+    }
+
+    // avro zigzag encoded long
+    fn long(&mut self) -> Result<i64, ASError> {
+        let mut n: u64 = 0;
+        let mut shift = 0;
+        loop {
+            let b = self.u8()?;
+            if shift > 63 {
+                return Err(invalid("varint"));
+            }
+            n |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                break;
+            }
+            shift += 7;
+        }
+        Ok((n >> 1) as i64 ^ -((n & 1) as i64))
+    }
+
+    fn avro_string(&mut self) -> Result<String, ASError> {
+        let len = usize::try_from(self.long()?).map_err(|_| invalid("tag length"))?;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| invalid("tag encoding"))
+    }
+}
+
+// tags are an avro array of { name: bytes, value: bytes } records
+fn decode_tags(bytes: &[u8]) -> Result<Vec<Tag>, ASError> {
+    let mut tags = vec![];
+    if bytes.is_empty() {
+        return Ok(tags);
+    }
+
+    let mut r = Reader { bytes, pos: 0 };
+    loop {
+        let mut count = r.long()?;
+        if count == 0 {
+            break;
+        }
+        if count < 0 {
+            // negative block count is followed by the block size in bytes
+            count = -count;
+            r.long()?;
+        }
+        for _ in 0..count {
+            tags.push(Tag {
+                name: r.avro_string()?,
+                value: r.avro_string()?,
+            });
+        }
+    }
+
+    Ok(tags)
+}
+
+#[cfg(test)]
+mod test {
+    use ethers::core::types::Signature;
+
+    use super::*;
+    use crate::everpay::LocalEthSigner;
+    use crate::test_vectors::{ETH_ADDRESS, ETH_PRIVATE_KEY};
+
+    fn item(tags: &[(&str, &str)], data: &[u8]) -> Vec<u8> {
+        let mut b = vec![];
+        b.extend_from_slice(&SIG_TYPE_ETHEREUM.to_le_bytes());
+        b.extend_from_slice(&[1u8; 65]);
+        b.extend_from_slice(&[2u8; 65]);
+        b.push(0);
+        b.push(1);
+        b.extend_from_slice(&[3u8; 32]);
+        let tags: Vec<(String, String)> = tags
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let encoded = encode_tags(&tags);
+        b.extend_from_slice(&(tags.len() as u64).to_le_bytes());
+        b.extend_from_slice(&(encoded.len() as u64).to_le_bytes());
+        b.extend_from_slice(&encoded);
+        b.extend_from_slice(data);
+        b
+    }
+
+    #[test]
+    fn it_parses_data_item() {
+        let bytes = item(
+            &[("Content-Type", "text/plain"), ("hello", "there")],
+            b"test",
+        );
+
+        let meta = parse_data_item(&bytes).unwrap();
+        let converted: ItemMetaRes = bytes.as_slice().try_into().unwrap();
+        assert_eq!(converted, meta);
+
+        assert_eq!(meta.signature_type, 3);
+        assert_eq!(meta.signature, Base64(vec![1u8; 65]).to_string());
+        assert_eq!(meta.owner, Base64(vec![2u8; 65]).to_string());
+        assert_eq!(meta.target, "");
+        assert_eq!(meta.anchor, Base64(vec![3u8; 32]).to_string());
+        assert_eq!(meta.data, Base64(b"test".to_vec()).to_string());
+        assert_eq!(
+            meta.tags,
+            vec![
+                Tag {
+                    name: "Content-Type".to_string(),
+                    value: "text/plain".to_string()
+                },
+                Tag {
+                    name: "hello".to_string(),
+                    value: "there".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            meta.id,
+            Base64(digest(&SHA256, &[1u8; 65]).as_ref().to_vec()).to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn it_signs_data_item_with_eth_key() {
+        let signer = LocalEthSigner::from_private_key(ETH_PRIVATE_KEY).unwrap();
+        let tags = vec![("Content-Type".to_string(), "text/plain".to_string())];
+
+        let bytes = sign_data_item(&signer, b"test", &tags, &[]).await.unwrap();
+        assert_eq!(
+            bytes.len(),
+            signed_data_item_len(&signer, 4, &tags, false).unwrap()
+        );
+
+        let meta = parse_data_item(&bytes).unwrap();
+        assert_eq!(meta.signature_type, SIG_TYPE_ETHEREUM as i64);
+        assert_eq!(meta.data, Base64(b"test".to_vec()).to_string());
+        assert_eq!(meta.tags[0].value, "text/plain");
+
+        // the owner is the key's public key and the signature recovers to its
+        // address over the item's deep hash
+        let owner = &bytes[67..132];
+        assert_eq!(
+            format!(
+                "0x{}",
+                ethers::utils::hex::encode(&ethers::utils::keccak256(&owner[1..])[12..])
+            ),
+            ETH_ADDRESS
+        );
+        let message = deep_hash(&[
+            b"dataitem",
+            b"1",
+            b"3",
+            owner,
+            &[],
+            &[],
+            &encode_tags(&tags),
+            b"test",
+        ]);
+        let sig = Signature::try_from(&bytes[2..67]).unwrap();
+        assert_eq!(format!("{:?}", sig.recover(message).unwrap()), ETH_ADDRESS);
+    }
+
+    #[tokio::test]
+    async fn it_anchors_data_items() {
+        let signer = LocalEthSigner::from_private_key(ETH_PRIVATE_KEY).unwrap();
+        let tags = vec![("Type".to_string(), "heartbeat".to_string())];
+
+        let sign = |anchor: Vec<u8>| {
+            let tags = tags.clone();
+            let signer = &signer;
+            async move { sign_data_item(signer, b"ok", &tags, &anchor).await }
+        };
+        let unanchored = parse_data_item(&sign(vec![]).await.unwrap()).unwrap();
+        // ethereum signatures are deterministic, without anchor the id repeats
+        assert_eq!(
+            parse_data_item(&sign(vec![]).await.unwrap()).unwrap().id,
+            unanchored.id
+        );
+
+        let anchor = random_anchor().unwrap();
+        let bytes = sign(anchor.to_vec()).await.unwrap();
+        assert_eq!(
+            bytes.len(),
+            signed_data_item_len(&signer, 2, &tags, true).unwrap()
+        );
+        let anchored = parse_data_item(&bytes).unwrap();
+        assert_eq!(anchored.anchor, Base64(anchor.to_vec()).to_string());
+        assert_ne!(anchored.id, unanchored.id);
+        assert_ne!(random_anchor().unwrap(), anchor);
+
+        assert!(sign(vec![1; 16]).await.is_err());
+    }
+
+    #[test]
+    fn it_rejects_truncated_data_item() {
+        let bytes = item(&[("hello", "there")], b"test");
+
+        assert!(parse_data_item(&bytes[..100]).is_err());
+        assert!(parse_data_item(&[9, 0]).is_err());
+        assert!(ItemMetaRes::try_from(&bytes[..100]).is_err());
+    }
+}
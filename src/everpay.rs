@@ -1,24 +1,69 @@
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use arloader::transaction::Base64;
 use arloader::Arweave;
 use async_trait::async_trait;
 use chrono::Utc;
+use ethers::core::k256::ecdsa::recoverable;
+use ethers::core::k256::elliptic_curve::sec1::ToEncodedPoint;
+use ethers::core::k256::FieldBytes;
+use ethers::core::types::Signature;
+use ethers::signers::coins_bip39::English;
+use ethers::signers::{LocalWallet, MnemonicBuilder, Signer as _};
+use reqwest::{Client, StatusCode};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use rust_decimal::Decimal;
+use url::Url;
 use walletconnect::{self, qr};
 
-use crate::arseeding_types::ASError;
+use crate::arseeding_types::{parse_url, ASError};
+use crate::client::{arweave_from_keypair_bytes, DEFAULT_ARWEAVE_URL};
 use crate::everpay_client::EverpayClient;
+use crate::everpay_types::ChainTarget;
 use crate::everpay_types::Signer;
+use crate::everpay_types::TokenFeeSummary;
 use crate::everpay_types::TokenInfo;
 use crate::everpay_types::TokenList;
 use crate::everpay_types::TX_ACTION_TRANSFER;
-use crate::everpay_types::{Balances, SignerType, StatusRes, Transaction, TX_VERSION_V1};
+use crate::everpay_types::{
+    Balances, Bundle, BundleData, BundleItem, BundleWithSigs, SignerType, StatusRes, Transaction,
+    TransactionRes, TxParams, MAX_TX_DATA_LEN, TX_ACTION_BUNDLE, TX_VERSION_V1,
+};
+use crate::util::{from_base_units, poll_until, to_base_units, Backoff};
+
+// delays between two balance checks in `wait_for_balance`
+const BALANCE_BACKOFF: Backoff = Backoff {
+    initial: Duration::from_secs(3),
+    max: Duration::from_secs(30),
+    factor: 2,
+};
+
+// delays between two tx status checks in `wait_for_tx`
+const TX_BACKOFF: Backoff = Backoff {
+    initial: Duration::from_secs(1),
+    max: Duration::from_secs(10),
+    factor: 2,
+};
+
+// time a bundle has to be executed in, see `Everpay::send_bundle`
+const BUNDLE_EXPIRATION: Duration = Duration::from_secs(100);
 
 pub struct Everpay {
     client: EverpayClient,
     signer: Arc<dyn Signer + Send + Sync>,
-    tokens: HashMap<String, TokenList>,
+    // behind a lock so that `refresh` works on a shared client
+    tokens: Arc<Mutex<Tokens>>,
+    max_tx_data_len: usize,
+}
+
+// what's kept of everpay's info
+#[derive(Default)]
+struct Tokens {
+    by_tag: HashMap<String, TokenList>,
     symbol_to_tag: HashMap<String, String>,
     fee_recipient: String,
 }
@@ -28,12 +73,11 @@ impl Everpay {
         client: EverpayClient,
         signer: Arc<dyn Signer + Send + Sync>,
     ) -> Result<Everpay, ASError> {
-        let mut c = Self {
+        let c = Self {
             client,
             signer,
-            tokens: HashMap::new(),
-            symbol_to_tag: HashMap::new(),
-            fee_recipient: String::from(""),
+            tokens: Arc::new(Mutex::new(Tokens::default())),
+            max_tx_data_len: MAX_TX_DATA_LEN,
         };
 
         c.update_info().await?;
@@ -41,7 +85,22 @@ impl Everpay {
         Ok(c)
     }
 
-    async fn update_info(&mut self) -> Result<(), ASError> {
+    // everpay paid with an ethereum private key (hex, with or without 0x), on
+    // `everpay_url` or `DEFAULT_EVERPAY_URL`
+    pub async fn from_eth_key(
+        private_key: &str,
+        everpay_url: Option<Url>,
+    ) -> Result<Everpay, ASError> {
+        let signer = Arc::new(LocalEthSigner::from_private_key(private_key)?);
+        let client = match everpay_url {
+            Some(url) => EverpayClient::new(Client::new(), url),
+            None => EverpayClient::default(),
+        };
+
+        Self::new(client, signer).await
+    }
+
+    async fn update_info(&self) -> Result<(), ASError> {
         let token_info = self.client.info().await?;
 
         let mut tokens = HashMap::new();
@@ -54,30 +113,193 @@ impl Everpay {
             tokens.insert(tag, t);
             sym_to_tags.insert(symbol, tag_2);
         }
-        self.tokens = tokens;
-        self.symbol_to_tag = sym_to_tags;
-        self.fee_recipient = token_info.fee_recipient;
+        *self.tokens.lock().unwrap() = Tokens {
+            by_tag: tokens,
+            symbol_to_tag: sym_to_tags,
+            fee_recipient: token_info.fee_recipient,
+        };
 
         Ok(())
     }
 
+    // reloads the token list, e.g. after everpay listed a new token
+    pub async fn refresh(&self) -> Result<(), ASError> {
+        self.update_info().await
+    }
+
     pub async fn info(&self) -> Result<TokenInfo, ASError> {
         self.client.info().await
     }
 
+    // longest tx `data` submitted, `MAX_TX_DATA_LEN` unless set otherwise
+    pub fn with_max_tx_data_len(mut self, max: usize) -> Self {
+        self.max_tx_data_len = max;
+        self
+    }
+
+    pub fn max_tx_data_len(&self) -> usize {
+        self.max_tx_data_len
+    }
+
     pub fn symbol_to_tag(&self) -> HashMap<String, String> {
-        self.symbol_to_tag.clone()
+        self.tokens.lock().unwrap().symbol_to_tag.clone()
     }
 
     pub fn tokens(&self) -> HashMap<String, TokenList> {
-        self.tokens.clone()
+        self.tokens.lock().unwrap().by_tag.clone()
+    }
+
+    pub fn token_by_symbol(&self, symbol: &str) -> Option<TokenList> {
+        let tokens = self.tokens.lock().unwrap();
+        tokens
+            .symbol_to_tag
+            .get(&symbol.to_lowercase())
+            .and_then(|tag| tokens.by_tag.get(tag))
+            .cloned()
+    }
+
+    fn token_by_tag(&self, tag: &str) -> Option<TokenList> {
+        self.tokens.lock().unwrap().by_tag.get(tag).cloned()
+    }
+
+    fn fee_recipient(&self) -> String {
+        self.tokens.lock().unwrap().fee_recipient.clone()
     }
 
     pub async fn balances(&self, account_id: &str) -> Result<Balances, ASError> {
         self.client.balances(account_id).await
     }
 
+    // Polls the account's balance until it holds at least `min_amount` (in
+    // human readable units) and returns it, e.g. while waiting for a deposit.
+    // Fails with `Timeout` if the balance is still lower after `timeout`.
+    pub async fn wait_for_balance(
+        &self,
+        account_id: &str,
+        symbol: &str,
+        min_amount: Decimal,
+        timeout: Duration,
+    ) -> Result<Decimal, ASError> {
+        let token = self.token_by_symbol(symbol).ok_or(ASError::TokenError {
+            arg: symbol.to_string(),
+        })?;
+        let token = &token;
+
+        poll_until(
+            || async move {
+                let balances = self.balances(account_id).await?;
+                // no entry is a zero balance, see `Balances::get`
+                match balances.get(&token.tag) {
+                    Some(b) => from_base_units(&b.amount, token.decimals),
+                    None => Ok(Decimal::ZERO),
+                }
+            },
+            |amount| *amount >= min_amount,
+            timeout,
+            BALANCE_BACKOFF,
+        )
+        .await
+        .map_err(|e| match e {
+            ASError::Timeout { e } => ASError::Timeout {
+                e: format!(
+                    "{} balance of {} still under {}, {}",
+                    symbol, account_id, min_amount, e
+                ),
+            },
+            e => e,
+        })
+    }
+
+    // Polls the tx until everpay reports a final status (see `TxStatus`) and
+    // returns it, failed txs included: check `tx_status()` on the result. A tx
+    // everpay doesn't know yet isn't final, other errors stop the polling.
+    // Fails with `Timeout` if the status is still not final after `timeout`.
+    pub async fn wait_for_tx(
+        &self,
+        ever_hash: &str,
+        timeout: Duration,
+    ) -> Result<TransactionRes, ASError> {
+        poll_until(
+            || self.client.find_transaction(ever_hash),
+            |res| matches!(res, Some(tx) if tx.tx_status().is_final()),
+            timeout,
+            TX_BACKOFF,
+        )
+        .await
+        .map(|res| res.unwrap_or_default())
+        .map_err(|e| match e {
+            ASError::Timeout { e } => ASError::Timeout {
+                e: format!("tx {} not final, {}", ever_hash, e),
+            },
+            e => e,
+        })
+    }
+
+    // every token with its fees in human readable units, sorted by symbol.
+    // Tokens whose fees can't be parsed are left out.
+    pub fn token_fee_table(&self) -> Vec<TokenFeeSummary> {
+        let mut table: Vec<TokenFeeSummary> = self
+            .tokens()
+            .values()
+            .filter_map(|t| {
+                Some(TokenFeeSummary {
+                    symbol: t.symbol.clone(),
+                    decimals: t.decimals,
+                    transfer_fee: from_base_units(&t.transfer_fee, t.decimals).ok()?,
+                    bundle_fee: from_base_units(&t.bundle_fee, t.decimals).ok()?,
+                })
+            })
+            .collect();
+        table.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+
+        table
+    }
+
+    // Address to send `symbol` to from `chain_type` (e.g. "ethereum") to
+    // deposit it on everpay, fetched from everpay's info.
+    pub async fn deposit_address(&self, symbol: &str, chain_type: &str) -> Result<String, ASError> {
+        let token = self.token_by_symbol(symbol).ok_or(ASError::TokenError {
+            arg: symbol.to_string(),
+        })?;
+        if !token.supports_chain(chain_type) {
+            return Err(ASError::ArgumentError {
+                arg: format!("{} can't be deposited from {}", symbol, chain_type),
+            });
+        }
+
+        let info = self.info().await?;
+        info.locker(chain_type)
+            .map(String::from)
+            .ok_or(ASError::ArgumentError {
+                arg: format!("everpay has no locker on {}", chain_type),
+            })
+    }
+
+    // chains `symbol` can be withdrawn to, empty for unknown tokens
+    pub fn withdrawable_chains(&self, symbol: &str) -> Vec<ChainTarget> {
+        match self.token_by_symbol(symbol) {
+            Some(t) => t.withdrawable_chains(),
+            None => vec![],
+        }
+    }
+
+    // converts a human readable amount (e.g. 1.5 USDC) to the token's base units
+    pub fn to_everpay_amount(&self, symbol: &str, value: Decimal) -> Result<u64, ASError> {
+        let token = self.token_by_symbol(symbol).ok_or(ASError::TokenError {
+            arg: symbol.to_string(),
+        })?;
+
+        to_base_units(symbol, value, token.decimals)
+    }
+
     pub async fn submit_tx(&self, tx: &Transaction) -> Result<StatusRes, ASError> {
+        if tx.data.len() > self.max_tx_data_len {
+            return Err(ASError::DataTooLarge {
+                size: tx.data.len(),
+                max: self.max_tx_data_len,
+            });
+        }
+
         self.client.submit_tx(tx).await
     }
 
@@ -85,6 +307,11 @@ impl Everpay {
         self.signer.sign(msg).await
     }
 
+    // the signer's address as it goes in a tx's `from`, see `everpay_address`
+    pub fn from_address(&self) -> Result<String, ASError> {
+        everpay_address(self.signer.signer_type(), &self.signer.wallet_address()?)
+    }
+
     pub async fn send_action_raw(
         &self,
         token_symbol: &str,
@@ -98,19 +325,37 @@ impl Everpay {
         amount: u64,
         data: &str,
     ) -> Result<StatusRes, ASError> {
-        let mut tx = Transaction {
+        self.send_tx_params(TxParams {
             token_symbol: token_symbol.to_string(),
             action: action.to_string(),
-            from: self.signer.wallet_address()?,
-            to: receiver.to_string(),
-            amount: amount.to_string(),
-            fee: fee.to_string(),
+            fee,
             fee_recipient: fee_recipient.to_string(),
-            nonce: self.get_nonce().to_string(),
             token_id: token_id.to_string(),
             chain_type: chain_type.to_string(),
             chain_id: chain_id.to_string(),
+            receiver: receiver.to_string(),
+            amount,
             data: data.to_string(),
+            nonce: None,
+        })
+        .await
+    }
+
+    // signs and submits a tx from the signer's account
+    pub async fn send_tx_params(&self, params: TxParams) -> Result<StatusRes, ASError> {
+        let mut tx = Transaction {
+            token_symbol: params.token_symbol,
+            action: params.action,
+            from: self.from_address()?,
+            to: params.receiver,
+            amount: params.amount.to_string(),
+            fee: params.fee.to_string(),
+            fee_recipient: params.fee_recipient,
+            nonce: self.get_nonce(params.nonce),
+            token_id: params.token_id,
+            chain_type: params.chain_type,
+            chain_id: params.chain_id,
+            data: params.data,
             version: TX_VERSION_V1.to_string(),
             sig: "".to_string(),
         };
@@ -127,11 +372,139 @@ impl Everpay {
         amount: u64,
         data: &str,
     ) -> Result<StatusRes, ASError> {
-        let tag = self.symbol_to_tag[&symbol.to_lowercase()].clone();
+        let tag = self
+            .symbol_to_tag()
+            .get(&symbol.to_lowercase())
+            .ok_or(ASError::TokenError {
+                arg: symbol.to_string(),
+            })?
+            .clone();
 
         self.send_transfer(&tag, receiver, amount, data).await
     }
 
+    // like `send_action_raw` but the token id, chain type/id and fee are taken
+    // from the token's everpay info. The token's transfer fee is used.
+    pub async fn send_action(
+        &self,
+        symbol: &str,
+        action: &str,
+        receiver: &str,
+        amount: u64,
+        data: &str,
+    ) -> Result<StatusRes, ASError> {
+        let token_info = self.token_by_symbol(symbol).ok_or(ASError::TokenError {
+            arg: symbol.to_string(),
+        })?;
+
+        self.send_token_action(&token_info, action, receiver, amount, data, None)
+            .await
+    }
+
+    // like `send_action` with an explicit nonce. A tx reusing the nonce of one
+    // that already landed is rejected
+    pub async fn send_action_with_nonce(
+        &self,
+        symbol: &str,
+        action: &str,
+        receiver: &str,
+        amount: u64,
+        data: &str,
+        nonce: u64,
+    ) -> Result<StatusRes, ASError> {
+        let token_info = self.token_by_symbol(symbol).ok_or(ASError::TokenError {
+            arg: symbol.to_string(),
+        })?;
+
+        self.send_token_action(&token_info, action, receiver, amount, data, Some(nonce))
+            .await
+    }
+
+    // Resends `original` with the same nonce and `new_fee` (in the token's base
+    // units). The resend is rejected if `original` already landed, so it can't
+    // be paid twice; everpay doesn't document whether a still pending
+    // `original` gets replaced. `original` must have been sent by this
+    // client's signer.
+    pub async fn replace_tx(
+        &self,
+        original: &Transaction,
+        new_fee: u64,
+    ) -> Result<StatusRes, ASError> {
+        let from = self.from_address()?;
+        if !original.from.eq_ignore_ascii_case(&from) {
+            return Err(ASError::ArgumentError {
+                arg: format!("tx is from {}, the signer is {}", original.from, from),
+            });
+        }
+
+        let mut tx = Transaction {
+            fee: new_fee.to_string(),
+            sig: String::from(""),
+            ..original.clone()
+        };
+        tx.sig = self.sign(&tx.sig_msg()).await?;
+
+        self.submit_tx(&tx).await
+    }
+
+    // item of a bundle sending `amount` (in base units) of `symbol` from the
+    // signer to `to`
+    pub fn bundle_item(&self, symbol: &str, to: &str, amount: u64) -> Result<BundleItem, ASError> {
+        let token = self.token_by_symbol(symbol).ok_or(ASError::TokenError {
+            arg: symbol.to_string(),
+        })?;
+
+        Ok(BundleItem {
+            tag: token.tag.clone(),
+            chain_id: token.chain_id.clone(),
+            from: self.from_address()?,
+            to: to.to_string(),
+            amount: amount.to_string(),
+        })
+    }
+
+    // Sends `items` as one everpay `bundle` tx: they all go through or none
+    // does, e.g. to split a payment among several recipients. Items must be
+    // sent from the signer's account as it's the only one signing the bundle.
+    // The bundle tx itself is in the first item's token.
+    pub async fn send_bundle(&self, items: Vec<BundleItem>) -> Result<StatusRes, ASError> {
+        let from = self.from_address()?;
+        for item in &items {
+            if !item.from.eq_ignore_ascii_case(&from) {
+                return Err(ASError::ArgumentError {
+                    arg: format!("bundle item is from {}, the signer is {}", item.from, from),
+                });
+            }
+        }
+        let token = match items.first() {
+            Some(item) => self.token_by_tag(&item.tag).ok_or(ASError::TokenError {
+                arg: item.tag.clone(),
+            })?,
+            None => {
+                return Err(ASError::ArgumentError {
+                    arg: String::from("empty bundle"),
+                })
+            }
+        };
+
+        let bundle = Bundle {
+            items,
+            expiration: Utc::now().timestamp() + BUNDLE_EXPIRATION.as_secs() as i64,
+            salt: random_salt()?,
+            version: TX_VERSION_V1.to_string(),
+        };
+        let sig = self.sign(&serde_json::to_string(&bundle)?).await?;
+
+        let mut sigs = HashMap::new();
+        sigs.insert(from.clone(), sig);
+        let data = serde_json::to_string(&BundleData {
+            bundle: BundleWithSigs { bundle, sigs },
+        })?;
+
+        self.send_token_action(&token, TX_ACTION_BUNDLE, &from, 0, &data, None)
+            .await
+    }
+
     async fn send_transfer(
         &self,
         token_tag: &str,
@@ -139,7 +512,7 @@ impl Everpay {
         amount: u64,
         data: &str,
     ) -> Result<StatusRes, ASError> {
-        let token_info = self.tokens.get(token_tag);
+        let token_info = self.token_by_tag(token_tag);
 
         if token_info.is_none() {
             return Err(ASError::TokenError {
@@ -148,30 +521,92 @@ impl Everpay {
         }
         let token_info = token_info.unwrap();
 
-        let mut tx = Transaction {
+        self.send_token_action(
+            &token_info,
+            TX_ACTION_TRANSFER,
+            receiver,
+            amount,
+            data,
+            None,
+        )
+        .await
+    }
+
+    async fn send_token_action(
+        &self,
+        token_info: &TokenList,
+        action: &str,
+        receiver: &str,
+        amount: u64,
+        data: &str,
+        nonce: Option<u64>,
+    ) -> Result<StatusRes, ASError> {
+        let fee = token_info
+            .transfer_fee
+            .parse::<u64>()
+            .map_err(|_| ASError::ArgumentError {
+                arg: format!("invalid transfer fee: {}", token_info.transfer_fee),
+            })?;
+
+        self.send_tx_params(TxParams {
             token_symbol: token_info.symbol.clone(),
-            action: TX_ACTION_TRANSFER.to_string(),
-            from: self.signer.wallet_address()?,
-            to: receiver.to_string(),
-            amount: amount.to_string(),
-            fee: token_info.transfer_fee.clone(),
-            fee_recipient: self.fee_recipient.clone(),
-            nonce: self.get_nonce(),
+            action: action.to_string(),
+            fee,
+            fee_recipient: self.fee_recipient(),
             token_id: token_info.id.clone(),
             chain_type: token_info.chain_type.clone(),
             chain_id: token_info.chain_id.clone(),
+            receiver: receiver.to_string(),
+            amount,
             data: data.to_string(),
-            version: TX_VERSION_V1.to_string(),
-            sig: String::from(""),
-        };
-
-        tx.sig = self.sign(&tx.sig_msg()).await?;
+            nonce,
+        })
+        .await
+    }
 
-        self.submit_tx(&tx).await
+    fn get_nonce(&self, nonce: Option<u64>) -> String {
+        match nonce {
+            Some(n) => n.to_string(),
+            None => (Utc::now().timestamp_nanos() / 1000000).to_string(),
+        }
     }
+}
+
+// random uuid (v4) telling apart bundles of the same transfers
+fn random_salt() -> Result<String, ASError> {
+    let mut b = [0u8; 16];
+    SystemRandom::new()
+        .fill(&mut b)
+        .map_err(|_| ASError::ArgumentError {
+            arg: String::from("no randomness for the bundle salt"),
+        })?;
+    b[6] = (b[6] & 0x0f) | 0x40;
+    b[8] = (b[8] & 0x3f) | 0x80;
 
-    fn get_nonce(&self) -> String {
-        (Utc::now().timestamp_nanos() / 1000000).to_string()
+    let hex: String = b.iter().map(|x| format!("{:02x}", x)).collect();
+    Ok(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    ))
+}
+
+// Ethereum addresses are EIP-55 checksummed, as everpay does when checking a tx
+// signature, whatever casing the signer gives them in. Other addresses are
+// kept as is.
+pub fn everpay_address(signer_type: SignerType, address: &str) -> Result<String, ASError> {
+    match signer_type {
+        SignerType::ECDSA | SignerType::Contract => {
+            let a: ethers::core::types::Address =
+                address.trim().parse().map_err(|_| ASError::ArgumentError {
+                    arg: format!("invalid ethereum address: {}", address),
+                })?;
+            Ok(ethers::utils::to_checksum(&a, None))
+        }
+        SignerType::RSA | SignerType::ED25519 => Ok(address.to_string()),
     }
 }
 
@@ -183,6 +618,11 @@ impl ArweaveSigner {
     pub fn new(arweave: Arweave) -> impl Signer {
         Self { arweave }
     }
+
+    pub fn from_keypair_bytes(jwk: &[u8]) -> Result<impl Signer, ASError> {
+        let arweave = arweave_from_keypair_bytes(jwk, parse_url(DEFAULT_ARWEAVE_URL)?)?;
+        Ok(Self { arweave })
+    }
 }
 
 #[async_trait]
@@ -211,21 +651,72 @@ impl Signer for ArweaveSigner {
 
         Ok(addr.to_string())
     }
+    async fn item_owner(&self) -> Result<Vec<u8>, ASError> {
+        Ok(self.arweave.crypto.keypair_modulus()?.0)
+    }
+    async fn sign_item(&self, deep_hash: &[u8]) -> Result<Vec<u8>, ASError> {
+        Ok(self.arweave.crypto.sign(deep_hash)?)
+    }
 }
 
 pub struct EthSigner {
     client: walletconnect::Client,
     account: String,
+    // public key of `account`, recovered from a first signature
+    item_owner: Mutex<Option<Vec<u8>>>,
 }
 
+// signed once by walletconnect wallets to learn their public key, which they
+// don't expose and items need as owner
+const ITEM_OWNER_MSG: &str = "sign this message to sign arseeding data items";
+
 impl EthSigner {
     pub async fn new(client: walletconnect::Client) -> impl Signer {
         let (accounts, _) = client.ensure_session(qr::print).await.unwrap();
         Self {
             client,
             account: format!("{:?}", accounts[0]),
+            item_owner: Mutex::new(None),
         }
     }
+
+    // personal_sign of raw bytes, as the 65 bytes r, s, v signature
+    async fn sign_bytes(&self, msg: &[u8]) -> Result<Vec<u8>, ASError> {
+        let msg = format!("0x{}", ethers::utils::hex::encode(msg));
+        let sig = self
+            .client
+            .personal_sign(&[&msg, &self.account])
+            .await
+            .map_err(|e| ASError::ArgumentError {
+                arg: format!("walletconnect: {:?}", e),
+            })?;
+
+        let sig = Signature::from_str(&sig.to_string()).map_err(|e| ASError::ArgumentError {
+            arg: format!("invalid eth signature: {}", e),
+        })?;
+        Ok(sig.to_vec())
+    }
+}
+
+// uncompressed public key that made `sig`, a personal_sign of `msg`
+fn recover_eth_public_key(msg: &[u8], sig: &[u8]) -> Result<Vec<u8>, ASError> {
+    let invalid = || ASError::ArgumentError {
+        arg: String::from("invalid eth signature"),
+    };
+    if sig.len() != 65 {
+        return Err(invalid());
+    }
+
+    // v is 27 or 28, k256 wants the recovery id
+    let mut sig = sig.to_vec();
+    sig[64] = sig[64].checked_sub(27).ok_or_else(invalid)?;
+    let sig = recoverable::Signature::try_from(sig.as_slice()).map_err(|_| invalid())?;
+
+    let hash = ethers::utils::hash_message(msg);
+    let key = sig
+        .recover_verifying_key_from_digest_bytes(FieldBytes::from_slice(hash.as_bytes()))
+        .map_err(|_| invalid())?;
+    Ok(key.to_encoded_point(false).as_bytes().to_vec())
 }
 
 #[async_trait]
@@ -250,6 +741,292 @@ impl Signer for EthSigner {
     fn wallet_address(&self) -> Result<String, ASError> {
         Ok(self.account.clone())
     }
+    async fn item_owner(&self) -> Result<Vec<u8>, ASError> {
+        if let Some(owner) = self.item_owner.lock().unwrap().clone() {
+            return Ok(owner);
+        }
+
+        let sig = self.sign_bytes(ITEM_OWNER_MSG.as_bytes()).await?;
+        let owner = recover_eth_public_key(ITEM_OWNER_MSG.as_bytes(), &sig)?;
+        *self.item_owner.lock().unwrap() = Some(owner.clone());
+        Ok(owner)
+    }
+    async fn sign_item(&self, deep_hash: &[u8]) -> Result<Vec<u8>, ASError> {
+        self.sign_bytes(deep_hash).await
+    }
+}
+
+// ed25519 keypair as used by Solana wallets. The message is signed as is and
+// everpay gets `{signature},{public key}` base64url encoded, like with arweave
+// keys. The account is the base58 public key shown by Solana wallets.
+pub struct SolanaSigner {
+    keypair: Ed25519KeyPair,
+}
+
+impl SolanaSigner {
+    // 64 bytes keypair, the secret seed followed by the public key, as stored
+    // in solana-keygen json files
+    pub fn from_keypair_bytes(keypair: &[u8]) -> Result<impl Signer, ASError> {
+        if keypair.len() != 64 {
+            return Err(ASError::ArgumentError {
+                arg: String::from("ed25519 keypair must be 64 bytes"),
+            });
+        }
+        let keypair = Ed25519KeyPair::from_seed_and_public_key(&keypair[..32], &keypair[32..])
+            .map_err(|_| ASError::ArgumentError {
+                arg: String::from("invalid ed25519 keypair"),
+            })?;
+        Ok(Self { keypair })
+    }
+
+    // 32 bytes secret seed
+    pub fn from_seed(seed: &[u8]) -> Result<impl Signer, ASError> {
+        let keypair =
+            Ed25519KeyPair::from_seed_unchecked(seed).map_err(|_| ASError::ArgumentError {
+                arg: String::from("invalid ed25519 seed"),
+            })?;
+        Ok(Self { keypair })
+    }
+}
+
+#[async_trait]
+impl Signer for SolanaSigner {
+    async fn sign(&self, msg: &str) -> Result<String, ASError> {
+        let sig = self.keypair.sign(msg.as_bytes());
+
+        Ok(format!(
+            "{},{}",
+            Base64(sig.as_ref().to_vec()),
+            self.owner()?
+        ))
+    }
+    fn owner(&self) -> Result<String, ASError> {
+        Ok(Base64(self.keypair.public_key().as_ref().to_vec()).to_string())
+    }
+    fn signer_type(&self) -> SignerType {
+        SignerType::ED25519
+    }
+    fn wallet_address(&self) -> Result<String, ASError> {
+        Ok(bs58::encode(self.keypair.public_key().as_ref()).into_string())
+    }
+    async fn item_owner(&self) -> Result<Vec<u8>, ASError> {
+        Ok(self.keypair.public_key().as_ref().to_vec())
+    }
+    async fn sign_item(&self, deep_hash: &[u8]) -> Result<Vec<u8>, ASError> {
+        Ok(self.keypair.sign(deep_hash).as_ref().to_vec())
+    }
+}
+
+// ECDSA signer holding the private key in memory, for servers and tests where
+// walletconnect can't be used. Signatures are deterministic (RFC 6979).
+pub struct LocalEthSigner {
+    wallet: LocalWallet,
+}
+
+impl LocalEthSigner {
+    pub fn new(wallet: LocalWallet) -> impl Signer {
+        Self { wallet }
+    }
+
+    // hex encoded private key, with or without the 0x prefix
+    pub fn from_private_key(key: &str) -> Result<impl Signer, ASError> {
+        let wallet = key
+            .trim_start_matches("0x")
+            .parse::<LocalWallet>()
+            .map_err(|_| ASError::ArgumentError {
+                arg: String::from("invalid eth private key"),
+            })?;
+        Ok(Self { wallet })
+    }
+
+    // key of account `account_index` of an english BIP-39 mnemonic, derived
+    // at m/44'/60'/0'/0/{account_index} as ethereum wallets do
+    pub fn from_mnemonic(phrase: &str, account_index: u32) -> Result<impl Signer, ASError> {
+        let wallet = MnemonicBuilder::<English>::default()
+            .phrase(phrase)
+            .derivation_path(&format!("m/44'/60'/0'/0/{}", account_index))
+            .and_then(|b| b.build())
+            .map_err(|_| ASError::ArgumentError {
+                arg: String::from("invalid eth mnemonic"),
+            })?;
+        Ok(Self { wallet })
+    }
+}
+
+#[async_trait]
+impl Signer for LocalEthSigner {
+    async fn sign(&self, msg: &str) -> Result<String, ASError> {
+        // personal_sign, the message is hashed with the Eth prefix
+        let sig = self
+            .wallet
+            .sign_message(msg)
+            .await
+            .map_err(|e| ASError::ArgumentError { arg: e.to_string() })?;
+
+        Ok(format!("0x{}", sig))
+    }
+    fn owner(&self) -> Result<String, ASError> {
+        Ok("".to_string())
+    }
+    fn signer_type(&self) -> SignerType {
+        SignerType::ECDSA
+    }
+    fn wallet_address(&self) -> Result<String, ASError> {
+        Ok(format!("{:?}", self.wallet.address()))
+    }
+    async fn item_owner(&self) -> Result<Vec<u8>, ASError> {
+        let key = self.wallet.signer().verifying_key();
+        Ok(key.to_encoded_point(false).as_bytes().to_vec())
+    }
+    async fn sign_item(&self, deep_hash: &[u8]) -> Result<Vec<u8>, ASError> {
+        let sig = self
+            .wallet
+            .sign_message(deep_hash)
+            .await
+            .map_err(|e| ASError::ArgumentError { arg: e.to_string() })?;
+
+        Ok(sig.to_vec())
+    }
+}
+
+// Canned signer for offline tests: no key, no crypto, every message gets
+// `fixed_sig`. Everpay would reject its transactions.
+#[cfg(any(test, feature = "testutil"))]
+pub struct MockSigner {
+    pub address: String,
+    pub fixed_sig: String,
+}
+
+#[cfg(any(test, feature = "testutil"))]
+#[async_trait]
+impl Signer for MockSigner {
+    async fn sign(&self, _msg: &str) -> Result<String, ASError> {
+        Ok(self.fixed_sig.clone())
+    }
+    fn owner(&self) -> Result<String, ASError> {
+        Ok("".to_string())
+    }
+    fn signer_type(&self) -> SignerType {
+        SignerType::ECDSA
+    }
+    fn wallet_address(&self) -> Result<String, ASError> {
+        Ok(self.address.clone())
+    }
+}
+
+// selector of `isValidSignature(bytes32,bytes)`, which is also what the
+// contract returns for a valid signature
+const EIP1271_MAGIC_VALUE: [u8; 4] = [0x16, 0x26, 0xba, 0x7e];
+
+// EIP-1271 contract wallet, e.g. a Safe. Messages are signed by `signer`, an
+// owner of the contract, and the signature only holds if the contract's
+// `isValidSignature` accepts it. `sign` asks the contract through `rpc_url`,
+// an ethereum node of the contract's chain, and fails if it doesn't.
+pub struct ContractSigner {
+    signer: Arc<dyn Signer + Send + Sync>,
+    contract: String,
+    client: Client,
+    rpc_url: Url,
+}
+
+impl ContractSigner {
+    pub fn new(signer: Arc<dyn Signer + Send + Sync>, contract: &str, rpc_url: Url) -> Self {
+        Self {
+            signer,
+            contract: contract.to_string(),
+            client: Client::new(),
+            rpc_url,
+        }
+    }
+
+    // Whether the contract accepts `sig` (hex) for `msg`, hashed as
+    // personal_sign does. Calls `isValidSignature` with `eth_call`.
+    pub async fn is_valid_signature(&self, msg: &str, sig: &str) -> Result<bool, ASError> {
+        let sig = ethers::utils::hex::decode(sig.trim_start_matches("0x")).map_err(|_| {
+            ASError::ArgumentError {
+                arg: format!("invalid signature: {}", sig),
+            }
+        })?;
+        let hash = ethers::utils::hash_message(msg);
+
+        let mut data = EIP1271_MAGIC_VALUE.to_vec();
+        data.extend(ethers::core::abi::encode(&[
+            ethers::core::abi::Token::FixedBytes(hash.as_bytes().to_vec()),
+            ethers::core::abi::Token::Bytes(sig),
+        ]));
+        let req = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_call",
+            "params": [
+                {
+                    "to": self.contract,
+                    "data": format!("0x{}", ethers::utils::hex::encode(data)),
+                },
+                "latest"
+            ],
+        });
+
+        let res = self
+            .client
+            .post(self.rpc_url.clone())
+            .json(&req)
+            .send()
+            .await?;
+        match res.status() {
+            StatusCode::OK => {}
+            status => {
+                return Err(ASError::APIError {
+                    e: format!("eth_call to {} failed: {}", self.rpc_url, status),
+                })
+            }
+        }
+        let res: serde_json::Value = res.json().await?;
+        // reverting is how some contracts reject a signature
+        if let Some(e) = res.get("error") {
+            if e["message"].as_str().unwrap_or_default().contains("revert") {
+                return Ok(false);
+            }
+            return Err(ASError::APIError {
+                e: format!("eth_call to {} failed: {}", self.rpc_url, e),
+            });
+        }
+
+        let result = res["result"].as_str().unwrap_or_default();
+        let result = ethers::utils::hex::decode(result.trim_start_matches("0x")).map_err(|_| {
+            ASError::APIError {
+                e: format!("invalid eth_call result: {}", result),
+            }
+        })?;
+        Ok(result.starts_with(&EIP1271_MAGIC_VALUE))
+    }
+}
+
+#[async_trait]
+impl Signer for ContractSigner {
+    async fn sign(&self, msg: &str) -> Result<String, ASError> {
+        let sig = self.signer.sign(msg).await?;
+        if !self.is_valid_signature(msg, &sig).await? {
+            return Err(ASError::ArgumentError {
+                arg: format!(
+                    "{} doesn't accept signatures of {}",
+                    self.contract,
+                    self.signer.wallet_address()?
+                ),
+            });
+        }
+
+        Ok(sig)
+    }
+    fn owner(&self) -> Result<String, ASError> {
+        self.signer.wallet_address()
+    }
+    fn signer_type(&self) -> SignerType {
+        SignerType::Contract
+    }
+    fn wallet_address(&self) -> Result<String, ASError> {
+        Ok(self.contract.clone())
+    }
 }
 
 #[cfg(test)]
@@ -262,10 +1039,286 @@ mod test {
     use walletconnect::Metadata;
 
     use super::*;
+    use crate::everpay_types::{Balance, TxStatus};
+    use crate::mock_server::{mock_signer, route_server};
 
     pub const CHAIN_TYPE: &str = "arweave,ethereum";
     pub const CHAIN_ID: &str = "0,1";
 
+    #[test]
+    fn it_normalizes_everpay_addresses() {
+        let checksummed = "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23";
+
+        for a in [
+            "0x2c7536e3605d9c16a7a3d7b1898e529396a65c23",
+            "0x2C7536E3605D9C16A7A3D7B1898E529396A65C23",
+            checksummed,
+        ] {
+            assert_eq!(everpay_address(SignerType::ECDSA, a).unwrap(), checksummed);
+            assert_eq!(
+                everpay_address(SignerType::Contract, a).unwrap(),
+                checksummed
+            );
+        }
+        assert!(everpay_address(SignerType::ECDSA, "0x2c75").is_err());
+        assert_eq!(
+            everpay_address(
+                SignerType::RSA,
+                "2NbYHgsuI8uQcuErDsgoRUCyj9X2wZ6PBN6WTz9xyu0"
+            )
+            .unwrap(),
+            "2NbYHgsuI8uQcuErDsgoRUCyj9X2wZ6PBN6WTz9xyu0"
+        );
+    }
+
+    #[tokio::test]
+    async fn it_checks_contract_signatures() {
+        let contract = "0x6451eB7f668de69Fb4C943Db72bCF2A73DeeC6B1";
+        let owner = Arc::new(
+            LocalEthSigner::from_private_key(crate::test_vectors::ETH_PRIVATE_KEY).unwrap(),
+        );
+        let rpc = |res: &str| {
+            route_server(
+                vec![(
+                    "POST /",
+                    200,
+                    format!(r#"{{"jsonrpc":"2.0","id":1,{}}}"#, res),
+                )],
+                false,
+            )
+        };
+
+        let (url, log) = rpc(&format!(r#""result":"0x1626ba7e{}""#, "0".repeat(56))).await;
+        let signer = ContractSigner::new(owner.clone(), contract, url);
+        let sig = signer.sign("hello").await.unwrap();
+        assert_eq!(sig, owner.sign("hello").await.unwrap());
+        assert_eq!(signer.wallet_address().unwrap(), contract);
+
+        let req: serde_json::Value = serde_json::from_slice(&log.lock().unwrap()[0].body).unwrap();
+        assert_eq!(req["method"], "eth_call");
+        assert_eq!(req["params"][0]["to"], contract);
+        let data = req["params"][0]["data"].as_str().unwrap();
+        let hash = ethers::utils::hex::encode(ethers::utils::hash_message("hello"));
+        assert!(data.starts_with(&format!("0x1626ba7e{}", hash)));
+        assert!(data.contains(sig.trim_start_matches("0x")));
+
+        // wrong magic value, then a revert
+        for res in [
+            format!(r#""result":"0x{}""#, "0".repeat(64)),
+            String::from(r#""error":{"code":3,"message":"execution reverted"}"#),
+        ] {
+            let (url, _) = rpc(&res).await;
+            let signer = ContractSigner::new(owner.clone(), contract, url);
+            assert!(!signer.is_valid_signature("hello", &sig).await.unwrap());
+            assert!(signer.sign("hello").await.is_err());
+        }
+    }
+
+    #[test]
+    fn it_generates_bundle_salts() {
+        let salt = random_salt().unwrap();
+
+        assert_eq!(salt.len(), 36);
+        assert_eq!(&salt[14..15], "4");
+        assert_ne!(salt, random_salt().unwrap());
+    }
+
+    #[tokio::test]
+    async fn it_recovers_eth_public_key() {
+        let signer =
+            LocalEthSigner::from_private_key(crate::test_vectors::ETH_PRIVATE_KEY).unwrap();
+        let sig = signer.sign_item(ITEM_OWNER_MSG.as_bytes()).await.unwrap();
+
+        assert_eq!(
+            recover_eth_public_key(ITEM_OWNER_MSG.as_bytes(), &sig).unwrap(),
+            signer.item_owner().await.unwrap()
+        );
+        assert!(recover_eth_public_key(ITEM_OWNER_MSG.as_bytes(), &sig[..64]).is_err());
+    }
+
+    // everpay listing USDC, deposited from ethereum, answering `routes` too
+    async fn mock_everpay(mut routes: Vec<(&str, u16, String)>) -> Everpay {
+        let info = TokenInfo {
+            eth_locker: "0x38741a69785e84399fcf7c5ad61d572f7ecb1dab".to_string(),
+            token_list: vec![TokenList {
+                tag: "ethereum-usdc-0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48".to_string(),
+                symbol: "USDC".to_string(),
+                decimals: 6,
+                chain_type: "ethereum".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        routes.push(("GET /info", 200, serde_json::to_string(&info).unwrap()));
+        let (url, _) = route_server(routes, false).await;
+
+        Everpay::new(
+            EverpayClient::new(Client::new(), url),
+            Arc::new(mock_signer()),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn it_waits_for_balance() {
+        let account = crate::test_vectors::ETH_ADDRESS;
+        let balances = |amount: &str| {
+            let balances = Balances {
+                accid: account.to_string(),
+                balances: vec![Balance {
+                    tag: "ethereum-usdc-0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48".to_string(),
+                    amount: amount.to_string(),
+                    decimals: 6,
+                }],
+            };
+            serde_json::to_string(&balances).unwrap()
+        };
+        let route = format!("GET /balances/{}", account);
+
+        let c = mock_everpay(vec![(&route, 200, balances("1500000"))]).await;
+        let res = c
+            .wait_for_balance(account, "USDC", Decimal::ONE, Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert_eq!(res, Decimal::new(15, 1));
+
+        // too low, then no entry at all
+        let empty = serde_json::to_string(&Balances::default()).unwrap();
+        for body in [balances("999999"), empty] {
+            let c = mock_everpay(vec![(&route, 200, body)]).await;
+            let res = c
+                .wait_for_balance(account, "USDC", Decimal::ONE, Duration::from_millis(10))
+                .await;
+            assert!(matches!(res, Err(ASError::Timeout { .. })));
+        }
+
+        let res = c
+            .wait_for_balance(account, "DAI", Decimal::ONE, Duration::from_millis(10))
+            .await;
+        assert!(matches!(res, Err(ASError::TokenError { .. })));
+    }
+
+    #[tokio::test]
+    async fn it_gets_deposit_address() {
+        let c = mock_everpay(vec![]).await;
+
+        assert_eq!(
+            c.deposit_address("USDC", "ethereum").await.unwrap(),
+            "0x38741a69785e84399fcf7c5ad61d572f7ecb1dab"
+        );
+        assert!(c.deposit_address("USDC", "arweave").await.is_err());
+        assert!(c.deposit_address("DAI", "ethereum").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn it_waits_for_tx() {
+        let ever_hash = "0x7ac8ec2d4ee5a1f3b1e4ea1e6f8f0d0c3f6e9a6e1e0b57b2f6d1d2c6d1e9c2a4";
+        let route = format!("GET /tx/{}", ever_hash);
+        let tx = |status: &str| {
+            serde_json::to_string(&TransactionRes {
+                status: status.to_string(),
+                ever_hash: ever_hash.to_string(),
+                ..Default::default()
+            })
+            .unwrap()
+        };
+
+        // failed txs are final too
+        for status in [TxStatus::Confirmed, TxStatus::Failed] {
+            let c = mock_everpay(vec![(&route, 200, tx(&String::from(status.clone())))]).await;
+            let res = c
+                .wait_for_tx(ever_hash, Duration::from_secs(1))
+                .await
+                .unwrap();
+            assert_eq!(res.tx_status(), status);
+            assert_eq!(res.ever_hash, ever_hash);
+        }
+
+        // not executed yet, then not known yet (404)
+        for routes in [vec![(route.as_str(), 200, tx("pending"))], vec![]] {
+            let c = mock_everpay(routes).await;
+            let res = c.wait_for_tx(ever_hash, Duration::from_millis(10)).await;
+            assert!(matches!(res, Err(ASError::Timeout { .. })));
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "outbound_calls"]
+    async fn it_sends_bundle() {
+        let signer = Arc::new(
+            LocalEthSigner::from_private_key(crate::test_vectors::ETH_PRIVATE_KEY).unwrap(),
+        );
+        let c = Everpay::new(EverpayClient::default(), signer)
+            .await
+            .unwrap();
+
+        let items = vec![
+            c.bundle_item("USDC", "2NbYHgsuI8uQcuErDsgoRUCyj9X2wZ6PBN6WTz9xyu0", 1)
+                .unwrap(),
+            c.bundle_item("USDC", "rQ3VdxFnCOYjquTF88UANCax8-viPtrmu5TA2dktQlY", 2)
+                .unwrap(),
+        ];
+        let res = c.send_bundle(items).await;
+
+        println!("{:#?}", res);
+    }
+
+    #[tokio::test]
+    #[ignore = "outbound_calls"]
+    async fn it_replaces_tx() {
+        let signer = Arc::new(
+            ArweaveSigner::from_keypair_bytes(crate::test_vectors::ARWEAVE_KEYFILE).unwrap(),
+        );
+        let c = Everpay::new(EverpayClient::default(), signer)
+            .await
+            .unwrap();
+
+        let token = c.token_by_symbol("AR").unwrap();
+        let original = Transaction {
+            token_symbol: token.symbol.clone(),
+            action: TX_ACTION_TRANSFER.to_string(),
+            from: c.from_address().unwrap(),
+            to: "rQ3VdxFnCOYjquTF88UANCax8-viPtrmu5TA2dktQlY".to_string(),
+            amount: "1".to_string(),
+            fee: "0".to_string(),
+            fee_recipient: c.fee_recipient(),
+            nonce: c.get_nonce(None),
+            token_id: token.id.clone(),
+            chain_type: token.chain_type.clone(),
+            chain_id: token.chain_id.clone(),
+            data: "".to_string(),
+            version: TX_VERSION_V1.to_string(),
+            sig: "".to_string(),
+        };
+
+        let res = c
+            .replace_tx(&original, token.transfer_fee.parse().unwrap())
+            .await;
+
+        println!("{:#?}", res);
+    }
+
+    #[tokio::test]
+    async fn it_rejects_invalid_eth_keys() {
+        let res = Everpay::from_eth_key("not a key", None).await;
+        assert!(res.is_err());
+    }
+
+    #[tokio::test]
+    #[ignore = "outbound_calls"]
+    async fn it_builds_from_eth_key() {
+        let c = Everpay::from_eth_key(
+            "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318",
+            None,
+        )
+        .await
+        .unwrap();
+
+        println!("{}", c.from_address().unwrap());
+        println!("{:#?}", c.symbol_to_tag());
+    }
+
     #[tokio::test]
     #[ignore = "outbound_calls"]
     async fn it_gets_info() {
@@ -365,6 +1418,37 @@ mod test {
         println!("{:#?}", res);
     }
 
+    #[tokio::test]
+    #[ignore = "outbound_calls"]
+    async fn it_sends_action() {
+        let arweave = Arweave::from_keypair_path(
+            PathBuf::from(
+                "./tests/fixtures/test-----arweave-keyfile-2NbYHgsuI8uQcuErDsgoRUCyj9X2wZ6PBN6WTz9xyu0.json",
+            ),
+            Url::from_str("https://arweave.net").unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let signer = Arc::new(ArweaveSigner::new(arweave));
+
+        let c = Everpay::new(EverpayClient::default(), signer)
+            .await
+            .unwrap();
+
+        let res = c
+            .send_action(
+                "AR",
+                TX_ACTION_TRANSFER,
+                "rQ3VdxFnCOYjquTF88UANCax8-viPtrmu5TA2dktQlY",
+                1,
+                r#"{"hello":"world","this":"is everpay"}"#,
+            )
+            .await;
+
+        println!("{:#?}", res);
+    }
+
     #[tokio::test]
     #[ignore = "outbound_calls"]
     async fn it_signs_and_sends_tx_arweave() {
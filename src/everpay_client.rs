@@ -1,13 +1,21 @@
+use futures::future::try_join_all;
+use futures::{stream, StreamExt, TryStreamExt};
 use reqwest::Client;
 use reqwest::StatusCode;
+use std::collections::HashMap;
 use std::str::FromStr;
 
 use url::Url;
 
-use crate::arseeding_types::{APIErrorRes, ASError};
+use crate::arseeding_types::{parse_url, ASError};
 use crate::everpay_types::TokenInfo;
 use crate::everpay_types::DEFAULT_EVERPAY_URL;
-use crate::everpay_types::{Balances, StatusRes, Transaction};
+use crate::everpay_types::{
+    AccountStatus, Balances, StatusRes, Transaction, TransactionRes, TxReceipt,
+};
+
+// `/balances` requests in flight at the same time in `balances_many`
+const BALANCES_CONCURRENCY: usize = 8;
 
 pub struct EverpayClient {
     client: Client,
@@ -28,20 +36,24 @@ impl EverpayClient {
         Self { client, url }
     }
 
+    pub fn from_url_str(client: reqwest::Client, url: &str) -> Result<EverpayClient, ASError> {
+        Ok(Self::new(client, parse_url(url)?))
+    }
+
     pub fn set_client(&mut self, c: Client) {
         self.client = c
     }
 
+    pub fn http_client(&self) -> &Client {
+        &self.client
+    }
+
     pub async fn info(&self) -> Result<TokenInfo, ASError> {
         let res = self.client.get(format!("{}info", self.url)).send().await?;
 
         match res.status() {
             StatusCode::OK => return Ok(res.json::<TokenInfo>().await?),
-            _ => {
-                return Err(ASError::APIError {
-                    e: res.json::<APIErrorRes>().await?.error,
-                })
-            }
+            _ => return Err(ASError::from_response(res).await),
         }
     }
 
@@ -54,11 +66,34 @@ impl EverpayClient {
 
         match res.status() {
             StatusCode::OK => return Ok(res.json::<Balances>().await?),
-            _ => {
-                return Err(ASError::APIError {
-                    e: res.json::<APIErrorRes>().await?.error,
-                })
-            }
+            _ => return Err(ASError::from_response(res).await),
+        }
+    }
+
+    // balances of all the accounts, keyed by account id, fetched concurrently.
+    // The first failed request fails the whole call.
+    pub async fn balances_many(
+        &self,
+        account_ids: &[String],
+    ) -> Result<HashMap<String, Balances>, ASError> {
+        stream::iter(account_ids)
+            .map(|id| async move { Ok((id.clone(), self.balances(id).await?)) })
+            .buffer_unordered(BALANCES_CONCURRENCY)
+            .try_collect()
+            .await
+    }
+
+    // everpay's account info for `account_id`, see `AccountStatus`
+    pub async fn account_status(&self, account_id: &str) -> Result<AccountStatus, ASError> {
+        let res = self
+            .client
+            .get(format!("{}account/{}", self.url, account_id))
+            .send()
+            .await?;
+
+        match res.status() {
+            StatusCode::OK => return Ok(res.json::<AccountStatus>().await?),
+            _ => return Err(ASError::from_response(res).await),
         }
     }
 
@@ -73,13 +108,62 @@ impl EverpayClient {
 
         match res.status() {
             StatusCode::OK => return Ok(res.json::<StatusRes>().await?),
-            _ => {
-                return Err(ASError::APIError {
-                    e: res.json::<APIErrorRes>().await?.error,
-                })
-            }
+            _ => return Err(ASError::from_response(res).await),
         }
     }
+
+    // receipt of a payment, the tx and its status. Not an inclusion proof, see
+    // `TxReceipt`
+    pub async fn tx_receipt(&self, ever_hash: &str) -> Result<TxReceipt, ASError> {
+        let res = self.get_transaction(ever_hash).await?;
+
+        Ok(TxReceipt {
+            ever_hash: res.ever_hash,
+            status: res.status,
+            sig_msg: res.tx.sig_msg(),
+            tx: res.tx,
+        })
+    }
+
+    pub async fn get_transaction(&self, ever_hash: &str) -> Result<TransactionRes, ASError> {
+        let res = self
+            .client
+            .get(format!("{}tx/{}", self.url, ever_hash))
+            .send()
+            .await?;
+
+        match res.status() {
+            StatusCode::OK => return Ok(res.json::<TransactionRes>().await?),
+            _ => return Err(ASError::from_response(res).await),
+        }
+    }
+
+    // like `get_transaction`, None while everpay doesn't know the tx (404),
+    // e.g. right after it was submitted
+    pub(crate) async fn find_transaction(
+        &self,
+        ever_hash: &str,
+    ) -> Result<Option<TransactionRes>, ASError> {
+        let res = self
+            .client
+            .get(format!("{}tx/{}", self.url, ever_hash))
+            .send()
+            .await?;
+
+        match res.status() {
+            StatusCode::OK => return Ok(Some(res.json::<TransactionRes>().await?)),
+            StatusCode::NOT_FOUND => return Ok(None),
+            _ => return Err(ASError::from_response(res).await),
+        }
+    }
+
+    // fetches all the transactions concurrently, results are in the same order as `ever_hashes`
+    pub async fn get_transactions(
+        &self,
+        ever_hashes: &[String],
+    ) -> Result<Vec<TransactionRes>, ASError> {
+        try_join_all(ever_hashes.iter().map(|h| self.get_transaction(h))).await
+    }
 }
 
 #[cfg(test)]
@@ -87,6 +171,15 @@ mod test {
 
     use super::*;
 
+    #[test]
+    fn it_rejects_invalid_urls() {
+        let res = EverpayClient::from_url_str(Client::new(), "not a url");
+        assert!(matches!(res, Err(ASError::URLError { .. })));
+
+        let c = EverpayClient::from_url_str(Client::new(), "https://api.everpay.io/v1").unwrap();
+        assert_eq!(c.url.as_str(), "https://api.everpay.io/v1/");
+    }
+
     #[tokio::test]
     #[ignore = "outbound_calls"]
     async fn it_gets_balance() {
@@ -99,6 +192,33 @@ mod test {
         println!("{:#?}", res);
     }
 
+    #[tokio::test]
+    #[ignore = "outbound_calls"]
+    async fn it_gets_balances_many() {
+        let c = EverpayClient::default();
+
+        let res = c
+            .balances_many(&[
+                "2NbYHgsuI8uQcuErDsgoRUCyj9X2wZ6PBN6WTz9xyu0".to_string(),
+                "0x4002ED1a1410aF1b4930cF6c479ae373dEbD6223".to_string(),
+            ])
+            .await;
+
+        println!("{:#?}", res);
+    }
+
+    #[tokio::test]
+    #[ignore = "outbound_calls"]
+    async fn it_gets_account_status() {
+        let c = EverpayClient::default();
+
+        let res = c
+            .account_status("2NbYHgsuI8uQcuErDsgoRUCyj9X2wZ6PBN6WTz9xyu0")
+            .await;
+
+        println!("{:#?}", res);
+    }
+
     #[tokio::test]
     #[ignore = "outbound_calls"]
     async fn it_gets_info() {
@@ -108,4 +228,30 @@ mod test {
 
         println!("{:#?}", res);
     }
+
+    #[tokio::test]
+    #[ignore = "outbound_calls"]
+    async fn it_gets_transactions() {
+        let c = EverpayClient::default();
+
+        let res = c
+            .get_transactions(&[
+                "0x7ac8ec2d4ee5a1f3b1e4ea1e6f8f0d0c3f6e9a6e1e0b57b2f6d1d2c6d1e9c2a4".to_string(),
+            ])
+            .await;
+
+        println!("{:#?}", res);
+    }
+
+    #[tokio::test]
+    #[ignore = "outbound_calls"]
+    async fn it_gets_tx_receipt() {
+        let c = EverpayClient::default();
+
+        let res = c
+            .tx_receipt("0x7ac8ec2d4ee5a1f3b1e4ea1e6f8f0d0c3f6e9a6e1e0b57b2f6d1d2c6d1e9c2a4")
+            .await;
+
+        println!("{:#?}", res);
+    }
 }
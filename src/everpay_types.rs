@@ -1,24 +1,91 @@
 use serde_derive::Deserialize;
 use serde_derive::Serialize;
 use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
 
-use crate::arseeding_types::ASError;
+use crate::arseeding_types::{string_or_number, ASError};
+use crate::util::from_base_units;
+use crate::data_item::{SIG_TYPE_ARWEAVE, SIG_TYPE_ED25519, SIG_TYPE_ETHEREUM, SIG_TYPE_SOLANA};
 use async_trait::async_trait;
+use rust_decimal::Decimal;
 
 pub const TX_VERSION_V1: &str = "v1";
 
 pub const TX_ACTION_TRANSFER: &str = "transfer";
 pub const TX_ACTION_MINT: &str     = "mint";
 pub const TX_ACTION_BURN: &str     = "burn";
+pub const TX_ACTION_BUNDLE: &str   = "bundle";
 
 
 
 pub const DEFAULT_EVERPAY_URL: &str = "https://api.everpay.io";
+pub const TESTNET_EVERPAY_URL: &str = "https://api-dev.everpay.io";
 
+// Default limit on a tx's `data`, checked before submitting and used to split
+// payments (see `Everpay::with_max_tx_data_len`). Everpay doesn't document
+// its limit, this is a conservative default rather than its exact value.
+pub const MAX_TX_DATA_LEN: usize = 8 * 1024;
 
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SignerType {
     ECDSA,
-    RSA
+    RSA,
+    // Solana style ed25519 keys
+    ED25519,
+    // EIP-1271 smart-contract wallet (Gnosis Safe, Argent...), the signature is
+    // checked through the contract's `isValidSignature` instead of ecrecover
+    Contract,
+}
+
+impl SignerType {
+    // ANS-104 signature type, contract wallets can't sign data items
+    pub fn ans104_code(&self) -> Option<u16> {
+        match self {
+            SignerType::RSA => Some(SIG_TYPE_ARWEAVE),
+            SignerType::ECDSA => Some(SIG_TYPE_ETHEREUM),
+            SignerType::ED25519 => Some(SIG_TYPE_SOLANA),
+            SignerType::Contract => None,
+        }
+    }
+
+    pub fn from_ans104_code(code: u16) -> Option<SignerType> {
+        match code {
+            SIG_TYPE_ARWEAVE => Some(SignerType::RSA),
+            SIG_TYPE_ETHEREUM => Some(SignerType::ECDSA),
+            SIG_TYPE_ED25519 | SIG_TYPE_SOLANA => Some(SignerType::ED25519),
+            _ => None,
+        }
+    }
+}
+
+impl Display for SignerType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignerType::ECDSA => write!(f, "ecdsa"),
+            SignerType::RSA => write!(f, "rsa"),
+            SignerType::ED25519 => write!(f, "ed25519"),
+            SignerType::Contract => write!(f, "contract"),
+        }
+    }
+}
+
+impl FromStr for SignerType {
+    type Err = ASError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ecdsa" => Ok(SignerType::ECDSA),
+            "rsa" => Ok(SignerType::RSA),
+            "ed25519" => Ok(SignerType::ED25519),
+            "contract" => Ok(SignerType::Contract),
+            _ => Err(ASError::ArgumentError {
+                arg: format!("unknown signer type: {}", s),
+            }),
+        }
+    }
 }
 
 #[async_trait]
@@ -27,6 +94,22 @@ pub trait Signer {
     fn owner(&self) -> Result<String, ASError>;
     fn wallet_address(&self) -> Result<String, ASError>;
     fn signer_type(&self) -> SignerType;
+
+    // owner field of the ANS-104 items signed by `sign_item` (raw public key
+    // or RSA modulus), see `data_item::sign_data_item`
+    async fn item_owner(&self) -> Result<Vec<u8>, ASError> {
+        Err(ASError::ArgumentError {
+            arg: format!("{} signer can't sign data items", self.signer_type()),
+        })
+    }
+
+    // raw signature of an item's deep hash, as ANS-104 expects for the
+    // signer's signature type
+    async fn sign_item(&self, _deep_hash: &[u8]) -> Result<Vec<u8>, ASError> {
+        Err(ASError::ArgumentError {
+            arg: format!("{} signer can't sign data items", self.signer_type()),
+        })
+    }
 }
 
 
@@ -38,10 +121,48 @@ pub struct Balances {
     pub balances: Vec<Balance>,
 }
 
+impl Balances {
+    // Balance of the token with `tag`. None means the account never held the
+    // token, i.e. a zero balance
+    pub fn get(&self, tag: &str) -> Option<&Balance> {
+        self.balances.iter().find(|b| b.tag == tag)
+    }
+
+    // `symbol_to_tag` as returned by `Everpay::symbol_to_tag`
+    pub fn by_symbol(&self, symbol: &str, symbol_to_tag: &HashMap<String, String>) -> Option<&Balance> {
+        symbol_to_tag
+            .get(&symbol.to_lowercase())
+            .and_then(|tag| self.get(tag))
+    }
+
+    // Sum of the balances in human readable units, each times `price_of(tag)`,
+    // e.g. the account's worth in USD given USD prices by token tag. Balances
+    // that can't be read or would overflow are left out.
+    pub fn total_value(&self, price_of: impl Fn(&str) -> Decimal) -> Decimal {
+        self.balances
+            .iter()
+            .filter_map(|b| {
+                let amount = from_base_units(&b.amount, b.decimals).ok()?;
+                amount.checked_mul(price_of(&b.tag))
+            })
+            .fold(Decimal::ZERO, |total, value| total.checked_add(value).unwrap_or(total))
+    }
+}
+
+impl<'a> IntoIterator for &'a Balances {
+    type Item = &'a Balance;
+    type IntoIter = std::slice::Iter<'a, Balance>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.balances.iter()
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Balance {
     pub tag: String,
+    #[serde(deserialize_with = "string_or_number")]
     pub amount: String,
     pub decimals: i64,
 }
@@ -53,7 +174,9 @@ pub struct Transaction {
     pub action: String,
     pub from: String,
     pub to: String,
+    #[serde(deserialize_with = "string_or_number")]
     pub amount: String,
+    #[serde(deserialize_with = "string_or_number")]
     pub fee: String,
     pub fee_recipient: String,
     pub nonce: String,
@@ -66,6 +189,11 @@ pub struct Transaction {
 }
 
 impl Transaction {
+    // the arseeding payment the tx carries in its `data`
+    pub fn pay_tx_data(&self) -> Result<PayTxData, ASError> {
+        PayTxData::from_tx_data(&self.data)
+    }
+
     pub fn sig_msg(&self) -> String {
        format!("tokenSymbol:{}\naction:{}\nfrom:{}\nto:{}\namount:{}\nfee:{}\nfeeRecipient:{}\nnonce:{}\ntokenID:{}\nchainType:{}\nchainID:{}\ndata:{}\nversion:{}", 
         self.token_symbol,
@@ -85,6 +213,23 @@ impl Transaction {
     }
 }
 
+// fields of a tx sent by `Everpay::send_tx_params`, amounts in the token's
+// base units. `nonce` overrides the clock based nonce, e.g. to resend a tx.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct TxParams {
+    pub token_symbol: String,
+    pub action: String,
+    pub fee: u64,
+    pub fee_recipient: String,
+    pub token_id: String,
+    pub chain_type: String,
+    pub chain_id: String,
+    pub receiver: String,
+    pub amount: u64,
+    pub data: String,
+    pub nonce: Option<u64>,
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TransactionRes {
@@ -93,11 +238,94 @@ pub struct TransactionRes {
     pub ever_hash: String,
 }
 
+impl TransactionRes {
+    pub fn tx_status(&self) -> TxStatus {
+        TxStatus::from(self.status.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum TxStatus {
+    // executed by everpay, balances are updated
+    Confirmed,
+    // confirmed and posted to arweave
+    Packaged,
+    Failed,
+    // a status this version doesn't know about, e.g. not executed yet
+    Unknown(String),
+}
+
+impl TxStatus {
+    // whether the status won't change anymore, see `Everpay::wait_for_tx`
+    pub fn is_final(&self) -> bool {
+        !matches!(self, TxStatus::Unknown(_))
+    }
+}
+
+impl From<&str> for TxStatus {
+    fn from(s: &str) -> Self {
+        match s {
+            "confirmed" => TxStatus::Confirmed,
+            "packaged" => TxStatus::Packaged,
+            "failed" => TxStatus::Failed,
+            _ => TxStatus::Unknown(s.to_string()),
+        }
+    }
+}
+
+impl From<String> for TxStatus {
+    fn from(s: String) -> Self {
+        TxStatus::from(s.as_str())
+    }
+}
+
+impl From<TxStatus> for String {
+    fn from(s: TxStatus) -> Self {
+        s.to_string()
+    }
+}
+
+impl Display for TxStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TxStatus::Confirmed => write!(f, "confirmed"),
+            TxStatus::Packaged => write!(f, "packaged"),
+            TxStatus::Failed => write!(f, "failed"),
+            TxStatus::Unknown(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+// Everpay doesn't expose merkle paths for single transactions, so this is no
+// inclusion proof: it's the signed tx (`sig` over `sig_msg` by `tx.from`), its
+// ever hash and the status everpay reports for it.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxReceipt {
+    pub ever_hash: String,
+    pub status: String,
+    pub tx: Transaction,
+    pub sig_msg: String,
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StatusRes {
     pub status: String,
 }
 
+// Everpay's view of an account. Only `accid` and `type` are relied on, the
+// rest of the response is kept as is in `fields`: everpay doesn't document
+// which of them, if any, tell whether the account may transact.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountStatus {
+    pub accid: String,
+    #[serde(default, rename = "type")]
+    pub account_type: String,
+    #[serde(flatten)]
+    pub fields: HashMap<String, serde_json::Value>,
+}
+
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -107,6 +335,48 @@ pub struct PayTxData {
     pub item_ids: Vec<String>
 }
 
+impl PayTxData {
+    // parses the `data` of an everpay tx paying for bundle items
+    pub fn from_tx_data(s: &str) -> Result<PayTxData, ASError> {
+        Ok(serde_json::from_str(s)?)
+    }
+}
+
+// one transfer of an everpay bundle, `amount` in the token's base units
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleItem {
+    pub tag: String,
+    #[serde(rename = "chainID")]
+    pub chain_id: String,
+    pub from: String,
+    pub to: String,
+    pub amount: String,
+}
+
+// Transfers executed together, all or none, if the bundle tx lands before
+// `expiration` (unix seconds). Every `from` account signs the json of the
+// bundle, the salt makes two bundles of the same transfers differ.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bundle {
+    pub items: Vec<BundleItem>,
+    pub expiration: i64,
+    pub salt: String,
+    pub version: String,
+}
+
+// `data` of a `bundle` tx: the bundle and its signatures by account
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleData {
+    pub bundle: BundleWithSigs,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleWithSigs {
+    #[serde(flatten)]
+    pub bundle: Bundle,
+    pub sigs: HashMap<String, String>,
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TokenInfo {
@@ -125,6 +395,26 @@ pub struct TokenInfo {
     pub token_list: Vec<TokenList>,
 }
 
+impl TokenInfo {
+    // address deposits from `chain_type` (e.g. "ethereum", "bsc") are sent
+    // to. `lockers` is keyed by chain type, older responses only have
+    // `eth_locker` and `ar_locker`.
+    pub fn locker(&self, chain_type: &str) -> Option<&str> {
+        let locker = match self.lockers.get(chain_type) {
+            Some(l) => l.as_str(),
+            None => match chain_type {
+                "ethereum" => self.eth_locker.as_str(),
+                "arweave" => self.ar_locker.as_str(),
+                _ => "",
+            },
+        };
+        if locker.is_empty() {
+            return None;
+        }
+        Some(locker)
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TokenList {
@@ -137,12 +427,45 @@ pub struct TokenList {
     #[serde(rename = "chainID")]
     pub chain_id: String,
     pub burn_fees: HashMap<String, String>,
+    #[serde(deserialize_with = "string_or_number")]
     pub transfer_fee: String,
+    #[serde(deserialize_with = "string_or_number")]
     pub bundle_fee: String,
     pub holder_num: i64,
     pub cross_chain_info_list: HashMap<String, CrossChainInfoListDetails>,
 }
 
+impl TokenList {
+    // whether the token can be deposited from `chain_type`: one of the comma
+    // separated `chain_type`s, e.g. "arweave,ethereum" for AR, or a cross
+    // chain target
+    pub fn supports_chain(&self, chain_type: &str) -> bool {
+        self.chain_type.split(',').any(|c| c == chain_type)
+            || self
+                .cross_chain_info_list
+                .values()
+                .any(|c| c.target_chain_type == chain_type)
+    }
+
+    // chains the token can be withdrawn to, sorted by chain type and id
+    pub fn withdrawable_chains(&self) -> Vec<ChainTarget> {
+        let mut chains: Vec<ChainTarget> = self
+            .cross_chain_info_list
+            .iter()
+            .map(|(key, c)| ChainTarget {
+                key: key.clone(),
+                chain_type: c.target_chain_type.clone(),
+                chain_id: c.target_chain_id.clone(),
+                decimals: c.target_decimals,
+                token_id: c.target_token_id.clone(),
+            })
+            .collect();
+        chains.sort_by(|a, b| (&a.chain_type, &a.chain_id).cmp(&(&b.chain_type, &b.chain_id)));
+
+        chains
+    }
+}
+
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -153,3 +476,230 @@ pub struct CrossChainInfoListDetails {
     pub target_token_id: String,
 }
 
+// a chain a token can be withdrawn to, from its `cross_chain_info_list`
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainTarget {
+    // the entry's key in `cross_chain_info_list`
+    pub key: String,
+    pub chain_type: String,
+    pub chain_id: String,
+    // the token's decimals on that chain, they can differ from everpay's
+    pub decimals: i64,
+    pub token_id: String,
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenFeeSummary {
+    pub symbol: String,
+    pub decimals: i64,
+    pub transfer_fee: Decimal,
+    pub bundle_fee: Decimal,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_resolves_lockers() {
+        let mut lockers = HashMap::new();
+        lockers.insert(String::from("bsc"), String::from("0xbsc"));
+        lockers.insert(String::from("ethereum"), String::from("0xeth"));
+        let info = TokenInfo {
+            eth_locker: String::from("0xold"),
+            ar_locker: String::from("arlocker"),
+            lockers,
+            ..Default::default()
+        };
+
+        assert_eq!(info.locker("ethereum"), Some("0xeth"));
+        assert_eq!(info.locker("bsc"), Some("0xbsc"));
+        assert_eq!(info.locker("arweave"), Some("arlocker"));
+        assert_eq!(info.locker("conflux"), None);
+
+        let mut ar = TokenList {
+            chain_type: String::from("arweave,ethereum"),
+            ..Default::default()
+        };
+        assert!(ar.supports_chain("arweave"));
+        assert!(ar.supports_chain("ethereum"));
+        assert!(!ar.supports_chain("bsc"));
+        ar.cross_chain_info_list.insert(
+            String::from("bsc"),
+            CrossChainInfoListDetails {
+                target_chain_type: String::from("bsc"),
+                ..Default::default()
+            },
+        );
+        assert!(ar.supports_chain("bsc"));
+    }
+
+    #[test]
+    fn it_reads_tx_status() {
+        let res = TransactionRes {
+            status: String::from("packaged"),
+            ..Default::default()
+        };
+        assert_eq!(res.tx_status(), TxStatus::Packaged);
+        assert!(res.tx_status().is_final());
+        assert!(TxStatus::from("failed").is_final());
+
+        let pending = TxStatus::from("pending");
+        assert_eq!(pending, TxStatus::Unknown(String::from("pending")));
+        assert!(!pending.is_final());
+        assert_eq!(pending.to_string(), "pending");
+    }
+
+    #[test]
+    fn it_looks_up_balances() {
+        let balance = |tag: &str, amount: &str| Balance {
+            tag: tag.to_string(),
+            amount: amount.to_string(),
+            decimals: 6,
+        };
+        let balances = Balances {
+            accid: String::from("0x2c7536e3605d9c16a7a3d7b1898e529396a65c23"),
+            balances: vec![balance("ethereum-usdc-0xa0b8", "10"), balance("arweave,ethereum-ar-AAAA", "20")],
+        };
+        let mut symbol_to_tag = HashMap::new();
+        symbol_to_tag.insert(String::from("usdc"), String::from("ethereum-usdc-0xa0b8"));
+        symbol_to_tag.insert(String::from("eth"), String::from("ethereum-eth-0x0000"));
+
+        assert_eq!(balances.get("arweave,ethereum-ar-AAAA").unwrap().amount, "20");
+        assert!(balances.get("unknown").is_none());
+        assert_eq!(balances.by_symbol("USDC", &symbol_to_tag).unwrap().amount, "10");
+        assert!(balances.by_symbol("eth", &symbol_to_tag).is_none());
+        assert_eq!((&balances).into_iter().count(), 2);
+    }
+
+    #[test]
+    fn it_sums_balance_values() {
+        let balance = |tag: &str, amount: &str, decimals: i64| Balance {
+            tag: tag.to_string(),
+            amount: amount.to_string(),
+            decimals,
+        };
+        let balances = Balances {
+            accid: String::from("0x2c7536e3605d9c16a7a3d7b1898e529396a65c23"),
+            balances: vec![
+                balance("ethereum-usdc-0xa0b8", "2500000", 6),
+                balance("arweave,ethereum-ar-AAAA", "500000000000", 12),
+                balance("ethereum-eth-0x0000", "not a number", 18),
+            ],
+        };
+        let price_of = |tag: &str| match tag {
+            "ethereum-usdc-0xa0b8" => Decimal::ONE,
+            "arweave,ethereum-ar-AAAA" => Decimal::new(8, 0),
+            _ => Decimal::new(1000, 0),
+        };
+
+        assert_eq!(balances.total_value(price_of), Decimal::new(65, 1));
+        assert_eq!(Balances::default().total_value(price_of), Decimal::ZERO);
+    }
+
+    #[test]
+    fn it_lists_withdrawable_chains() {
+        let token: TokenList = serde_json::from_value(serde_json::json!({
+            "tag": "bsc-usdc-0x8ac7",
+            "id": "0x8ac7",
+            "symbol": "USDC",
+            "decimals": 18,
+            "totalSupply": "0",
+            "chainType": "bsc",
+            "chainID": "56",
+            "burnFees": {},
+            "transferFee": "0",
+            "bundleFee": "0",
+            "holderNum": 0,
+            "crossChainInfoList": {
+                "ethereum": {
+                    "targetChainId": "1",
+                    "targetChainType": "ethereum",
+                    "targetDecimals": 6,
+                    "targetTokenId": "0xa0b8"
+                },
+                "bsc": {
+                    "targetChainId": "56",
+                    "targetChainType": "bsc",
+                    "targetDecimals": 18,
+                    "targetTokenId": "0x8ac7"
+                }
+            }
+        }))
+        .unwrap();
+
+        let chains = token.withdrawable_chains();
+        assert_eq!(chains.len(), 2);
+        assert_eq!(chains[0].key, "bsc");
+        assert_eq!(chains[0].decimals, 18);
+        assert_eq!(chains[1].chain_type, "ethereum");
+        assert_eq!(chains[1].chain_id, "1");
+        assert_eq!(chains[1].decimals, 6);
+        assert_eq!(chains[1].token_id, "0xa0b8");
+        assert!(TokenList::default().withdrawable_chains().is_empty());
+    }
+
+    #[test]
+    fn it_reads_account_status() {
+        let status: AccountStatus = serde_json::from_str(
+            r#"{"accid":"0x2c7536e3605d9c16a7a3d7b1898e529396a65c23","type":"ethereum","nonce":3}"#,
+        )
+        .unwrap();
+        assert_eq!(status.account_type, "ethereum");
+        assert_eq!(status.fields["nonce"], 3);
+    }
+
+    #[test]
+    fn it_serializes_bundles() {
+        let bundle = Bundle {
+            items: vec![BundleItem {
+                tag: String::from("ethereum-usdc-0xa0b8"),
+                chain_id: String::from("1"),
+                from: String::from("0x2c7536E3605D9C16a7a3D7b1898e529396a65c23"),
+                to: String::from("2NbYHgsuI8uQcuErDsgoRUCyj9X2wZ6PBN6WTz9xyu0"),
+                amount: String::from("100"),
+            }],
+            expiration: 1656000060,
+            salt: String::from("salt"),
+            version: String::from("v1"),
+        };
+        assert_eq!(
+            serde_json::to_string(&bundle).unwrap(),
+            r#"{"items":[{"tag":"ethereum-usdc-0xa0b8","chainID":"1","from":"0x2c7536E3605D9C16a7a3D7b1898e529396a65c23","to":"2NbYHgsuI8uQcuErDsgoRUCyj9X2wZ6PBN6WTz9xyu0","amount":"100"}],"expiration":1656000060,"salt":"salt","version":"v1"}"#
+        );
+
+        let mut sigs = HashMap::new();
+        sigs.insert(bundle.items[0].from.clone(), String::from("0xsig"));
+        let data = BundleData {
+            bundle: BundleWithSigs { bundle, sigs },
+        };
+        let json = serde_json::to_string(&data).unwrap();
+        assert!(json.ends_with(r#""version":"v1","sigs":{"0x2c7536E3605D9C16a7a3D7b1898e529396a65c23":"0xsig"}}}"#));
+        assert_eq!(serde_json::from_str::<BundleData>(&json).unwrap(), data);
+    }
+
+    #[test]
+    fn it_parses_pay_tx_data() {
+        let pay = PayTxData {
+            app_name: String::from("arseeding"),
+            action: String::from("payment"),
+            item_ids: vec![String::from("BewjUEppPQ9pljVrjMxF7A2Kkz5ZJt_Q7tXRkQDm2VQ")],
+        };
+        let tx = Transaction {
+            data: serde_json::to_string(&pay).unwrap(),
+            ..Default::default()
+        };
+
+        assert_eq!(tx.pay_tx_data().unwrap(), pay);
+        assert_eq!(
+            PayTxData::from_tx_data(r#"{"appName":"arseeding","action":"payment","itemIds":[]}"#)
+                .unwrap()
+                .item_ids,
+            Vec::<String>::new()
+        );
+        assert!(PayTxData::from_tx_data(r#"{"hello":"world"}"#).is_err());
+    }
+}
@@ -1,7 +1,15 @@
 pub mod client;
 
 pub mod arseeding_types;
+pub mod data_item;
+pub mod tags;
+pub mod util;
 
 pub mod everpay;
 pub mod everpay_client;
 pub mod everpay_types;
+
+#[cfg(test)]
+mod mock_server;
+#[cfg(test)]
+mod test_vectors;
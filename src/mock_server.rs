@@ -0,0 +1,141 @@
+// Local http servers standing in for arseeding and everpay in tests
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use url::Url;
+
+use crate::arseeding_types::parse_url;
+use crate::everpay::MockSigner;
+
+pub(crate) fn mock_signer() -> MockSigner {
+    MockSigner {
+        address: "0x2c7536e3605d9c16a7a3d7b1898e529396a65c23".to_string(),
+        fixed_sig: "0x00".to_string(),
+    }
+}
+
+// request received by `route_server`
+pub(crate) struct MockRequest {
+    // method and target, e.g. "GET /bundle/tx/id/data"
+    pub(crate) line: String,
+    // header lines, lowercased
+    pub(crate) headers: String,
+    pub(crate) body: Vec<u8>,
+}
+
+// Reads the head of the next request off `socket`, returned with an empty
+// body and its `Content-Length`. `buf` keeps what's been read past it.
+// None once the connection is closed.
+async fn read_head(socket: &mut TcpStream, buf: &mut Vec<u8>) -> Option<(MockRequest, usize)> {
+    let mut chunk = vec![0u8; 4096];
+    let header_end = loop {
+        if let Some(i) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break i + 4;
+        }
+        let n = socket.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+
+    let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+    let (request_line, headers) = head.split_once("\r\n")?;
+    let line = request_line.rsplit_once(' ')?.0.to_string();
+    let headers = headers.to_lowercase();
+    let len = headers
+        .lines()
+        .find_map(|l| l.strip_prefix("content-length:"))
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    buf.drain(..header_end);
+
+    Some((
+        MockRequest {
+            line,
+            headers,
+            body: vec![],
+        },
+        len,
+    ))
+}
+
+// Reads the `len` bytes body following a head read by `read_head`
+async fn read_body(socket: &mut TcpStream, buf: &mut Vec<u8>, len: usize) -> Option<Vec<u8>> {
+    let mut chunk = vec![0u8; 4096];
+    while buf.len() < len {
+        let n = socket.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    Some(buf.drain(..len).collect())
+}
+
+// Http server answering `routes`: request line ("GET /info", query
+// included or not) -> status and body. Other requests get a 404, or are
+// left unanswered with `hang`, their body unread, e.g. to test timeouts.
+// Every request is recorded in the returned log.
+pub(crate) async fn route_server(
+    routes: Vec<(&str, u16, String)>,
+    hang: bool,
+) -> (Url, Arc<Mutex<Vec<MockRequest>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = parse_url(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+    let routes: Arc<HashMap<String, (u16, String)>> = Arc::new(
+        routes
+            .into_iter()
+            .map(|(line, status, body)| (line.to_string(), (status, body)))
+            .collect(),
+    );
+    let log = Arc::new(Mutex::new(vec![]));
+
+    let requests = log.clone();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let routes = routes.clone();
+            let requests = requests.clone();
+            tokio::spawn(async move {
+                let mut buf = vec![];
+                while let Some((mut req, len)) = read_head(&mut socket, &mut buf).await {
+                    let path = req.line.split('?').next().unwrap_or_default();
+                    let route = routes.get(&req.line).or_else(|| routes.get(path)).cloned();
+                    if route.is_none() && hang {
+                        requests.lock().unwrap().push(req);
+                        tokio::time::sleep(Duration::from_secs(60)).await;
+                        return;
+                    }
+                    req.body = match read_body(&mut socket, &mut buf, len).await {
+                        Some(body) => body,
+                        None => return,
+                    };
+                    requests.lock().unwrap().push(req);
+
+                    let (status, body) = route.unwrap_or((404, String::from("not found")));
+                    let res = format!(
+                        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                        status,
+                        StatusCode::from_u16(status)
+                            .unwrap()
+                            .canonical_reason()
+                            .unwrap_or_default(),
+                        body.len(),
+                        body
+                    );
+                    if socket.write_all(res.as_bytes()).await.is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+
+    (url, log)
+}
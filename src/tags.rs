@@ -0,0 +1,270 @@
+use chrono::Utc;
+use ring::digest::{digest, SHA256};
+use std::collections::HashMap;
+
+use crate::arseeding_types::ASError;
+
+// conventional tag names indexed by arweave gateways
+pub const CONTENT_TYPE: &str = "Content-Type";
+pub const APP_NAME: &str = "App-Name";
+pub const APP_VERSION: &str = "App-Version";
+pub const UNIX_TIME: &str = "Unix-Time";
+pub const BUNDLE_FORMAT: &str = "Bundle-Format";
+pub const BUNDLE_VERSION: &str = "Bundle-Version";
+pub const CONTENT_DISPOSITION: &str = "Content-Disposition";
+pub const CONTENT_SHA256: &str = "Content-SHA256";
+
+// ANS-104 limits on a data item's tags, in bytes
+pub const MAX_TAGS: usize = 128;
+pub const MAX_TAG_NAME_LEN: usize = 1024;
+pub const MAX_TAG_VALUE_LEN: usize = 3072;
+pub const MAX_TAGS_LEN: usize = 4096;
+
+// common values for the `Content-Type` tag
+pub const CONTENT_TYPE_OCTET_STREAM: &str = "application/octet-stream";
+pub const CONTENT_TYPE_JSON: &str = "application/json";
+pub const CONTENT_TYPE_CBOR: &str = "application/cbor";
+pub const CONTENT_TYPE_TEXT: &str = "text/plain";
+pub const CONTENT_TYPE_HTML: &str = "text/html";
+
+pub fn app_tags(name: &str, version: &str) -> HashMap<String, String> {
+    let mut tags = HashMap::new();
+    tags.insert(APP_NAME.to_string(), name.to_string());
+    tags.insert(APP_VERSION.to_string(), version.to_string());
+    tags
+}
+
+pub fn content_type_tag(content_type: &str) -> HashMap<String, String> {
+    let mut tags = HashMap::new();
+    tags.insert(CONTENT_TYPE.to_string(), content_type.to_string());
+    tags
+}
+
+// content type of `data` from its magic number, octet-stream when it isn't
+// recognized
+pub fn guess_content_type(data: &[u8]) -> &'static str {
+    infer::get(data)
+        .map(|t| t.mime_type())
+        .unwrap_or(CONTENT_TYPE_OCTET_STREAM)
+}
+
+// `tags` with a guessed `Content-Type` (see `guess_content_type`) when they
+// don't have one
+pub fn with_guessed_content_type(
+    data: &[u8],
+    mut tags: HashMap<String, String>,
+) -> HashMap<String, String> {
+    if !tags.contains_key(CONTENT_TYPE) {
+        tags.insert(
+            CONTENT_TYPE.to_string(),
+            guess_content_type(data).to_string(),
+        );
+    }
+    tags
+}
+
+// gateways serve the item with this header, so downloads are saved as
+// `filename` instead of the item id
+pub fn filename_tag(filename: &str) -> HashMap<String, String> {
+    let escaped = filename.replace('\\', "\\\\").replace('"', "\\\"");
+    let mut tags = HashMap::new();
+    tags.insert(
+        CONTENT_DISPOSITION.to_string(),
+        format!("attachment; filename=\"{}\"", escaped),
+    );
+    tags
+}
+
+// hex encoded sha-256 of `data`, uploads of the same content can then be found
+// by querying gateways for the tag
+pub fn content_sha256_tag(data: &[u8]) -> HashMap<String, String> {
+    let hash: String = digest(&SHA256, data)
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let mut tags = HashMap::new();
+    tags.insert(CONTENT_SHA256.to_string(), hash);
+    tags
+}
+
+pub fn unix_time_tag() -> HashMap<String, String> {
+    let mut tags = HashMap::new();
+    tags.insert(UNIX_TIME.to_string(), Utc::now().timestamp().to_string());
+    tags
+}
+
+// Merges `tags` on top of `defaults`: when a name is in both, the value from
+// `tags` is kept. The client uses it to layer, from lowest to highest
+// precedence, its default tags, the `Unix-Time` tag and the call's tags.
+pub fn merge_tags(
+    defaults: &HashMap<String, String>,
+    tags: &HashMap<String, String>,
+) -> HashMap<String, String> {
+    let mut merged = defaults.clone();
+    merged.extend(tags.iter().map(|(k, v)| (k.clone(), v.clone())));
+    merged
+}
+
+// Checks `tags` against the ANS-104 limits: at most `MAX_TAGS` tags, non empty
+// names and values up to `MAX_TAG_NAME_LEN` and `MAX_TAG_VALUE_LEN` bytes and
+// `MAX_TAGS_LEN` bytes of names and values overall.
+pub fn validate_tags(tags: &HashMap<String, String>) -> Result<(), ASError> {
+    let invalid = |arg: String| Err(ASError::ArgumentError { arg });
+
+    if tags.len() > MAX_TAGS {
+        return invalid(format!(
+            "{} tags, at most {} are allowed",
+            tags.len(),
+            MAX_TAGS
+        ));
+    }
+
+    let mut total = 0;
+    for (name, value) in sorted_tags(tags) {
+        if name.is_empty() {
+            return invalid(String::from("empty tag name"));
+        }
+        if value.is_empty() {
+            return invalid(format!("tag {} has an empty value", name));
+        }
+        if name.len() > MAX_TAG_NAME_LEN {
+            return invalid(format!(
+                "tag name {}... is {} bytes, at most {} are allowed",
+                name.chars().take(32).collect::<String>(),
+                name.len(),
+                MAX_TAG_NAME_LEN
+            ));
+        }
+        if value.len() > MAX_TAG_VALUE_LEN {
+            return invalid(format!(
+                "tag {} value is {} bytes, at most {} are allowed",
+                name,
+                value.len(),
+                MAX_TAG_VALUE_LEN
+            ));
+        }
+        total += name.len() + value.len();
+    }
+
+    if total > MAX_TAGS_LEN {
+        return invalid(format!(
+            "tags are {} bytes, at most {} are allowed",
+            total, MAX_TAGS_LEN
+        ));
+    }
+    Ok(())
+}
+
+// tags ordered by name, so the same set always produces the same item tags
+pub fn sorted_tags(tags: &HashMap<String, String>) -> Vec<(String, String)> {
+    let mut sorted: Vec<(String, String)> =
+        tags.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+    sorted.sort();
+    sorted
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_builds_content_sha256_tag() {
+        assert_eq!(
+            content_sha256_tag(b"hello world")[CONTENT_SHA256],
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+        );
+        assert_eq!(
+            content_sha256_tag(b"")[CONTENT_SHA256],
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn it_validates_tags() {
+        let tags = |pairs: &[(String, String)]| -> HashMap<String, String> {
+            pairs.iter().cloned().collect()
+        };
+        let tag = |name: &str, value: &str| (name.to_string(), value.to_string());
+
+        assert!(validate_tags(&HashMap::new()).is_ok());
+        assert!(validate_tags(&app_tags("my-app", "1.0.0")).is_ok());
+        assert!(validate_tags(&tags(&[tag("", "value")])).is_err());
+        assert!(validate_tags(&tags(&[tag("name", "")])).is_err());
+
+        let name = "n".repeat(MAX_TAG_NAME_LEN);
+        assert!(validate_tags(&tags(&[tag(&name, "v")])).is_ok());
+        assert!(validate_tags(&tags(&[tag(&format!("{}n", name), "v")])).is_err());
+
+        let value = "v".repeat(MAX_TAG_VALUE_LEN);
+        assert!(validate_tags(&tags(&[tag("n", &value)])).is_ok());
+        assert!(validate_tags(&tags(&[tag("n", &format!("{}v", value))])).is_err());
+
+        // each tag is fine on its own, not all together
+        assert!(validate_tags(&tags(&[tag("a", &value), tag("b", &value)])).is_err());
+
+        let many: Vec<(String, String)> =
+            (0..=MAX_TAGS).map(|i| tag(&i.to_string(), "v")).collect();
+        assert!(validate_tags(&tags(&many[..MAX_TAGS])).is_ok());
+        assert!(matches!(
+            validate_tags(&tags(&many)),
+            Err(ASError::ArgumentError { .. })
+        ));
+    }
+
+    #[test]
+    fn it_builds_app_tags() {
+        let tags = app_tags("my-app", "1.0.0");
+
+        assert_eq!(tags.len(), 2);
+        assert_eq!(tags[APP_NAME], "my-app");
+        assert_eq!(tags[APP_VERSION], "1.0.0");
+    }
+
+    #[test]
+    fn it_builds_filename_tag() {
+        assert_eq!(
+            filename_tag("report.pdf")[CONTENT_DISPOSITION],
+            "attachment; filename=\"report.pdf\""
+        );
+        assert_eq!(
+            filename_tag(r#"a "quoted" \name"#)[CONTENT_DISPOSITION],
+            r#"attachment; filename="a \"quoted\" \\name""#
+        );
+    }
+
+    #[test]
+    fn it_guesses_content_types() {
+        let png = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0, 0];
+        assert_eq!(guess_content_type(&png), "image/png");
+        assert_eq!(guess_content_type(b"hello"), CONTENT_TYPE_OCTET_STREAM);
+
+        let guessed = with_guessed_content_type(&png, HashMap::new());
+        assert_eq!(guessed[CONTENT_TYPE], "image/png");
+        let kept = with_guessed_content_type(&png, content_type_tag(CONTENT_TYPE_JSON));
+        assert_eq!(kept[CONTENT_TYPE], CONTENT_TYPE_JSON);
+    }
+
+    #[test]
+    fn it_merges_tags_with_caller_precedence() {
+        let mut defaults = app_tags("my-app", "1.0.0");
+        defaults.insert(CONTENT_TYPE.to_string(), CONTENT_TYPE_TEXT.to_string());
+
+        let mut tags = content_type_tag(CONTENT_TYPE_JSON);
+        tags.insert("hello".to_string(), "there".to_string());
+
+        let merged = merge_tags(&defaults, &tags);
+
+        assert_eq!(
+            sorted_tags(&merged),
+            vec![
+                (APP_NAME.to_string(), "my-app".to_string()),
+                (APP_VERSION.to_string(), "1.0.0".to_string()),
+                (CONTENT_TYPE.to_string(), CONTENT_TYPE_JSON.to_string()),
+                ("hello".to_string(), "there".to_string()),
+            ]
+        );
+        assert_eq!(merge_tags(&defaults, &HashMap::new()), defaults);
+        assert_eq!(merge_tags(&HashMap::new(), &tags), tags);
+    }
+}
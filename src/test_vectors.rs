@@ -0,0 +1,191 @@
+// Golden vectors for the everpay signing path. Nothing here needs the network,
+// a change to `sig_msg` or to a signer fails these tests instead of payments.
+
+use arloader::transaction::Base64;
+use std::str::FromStr;
+
+use crate::arseeding_types::parse_url;
+use crate::client::{arweave_from_keypair_bytes, DEFAULT_ARWEAVE_URL};
+use crate::everpay::{ArweaveSigner, LocalEthSigner, SolanaSigner};
+use crate::everpay_types::{Signer, Transaction, TX_ACTION_TRANSFER, TX_VERSION_V1};
+
+// well known test key, never fund it
+pub(crate) const ETH_PRIVATE_KEY: &str =
+    "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+pub(crate) const ETH_ADDRESS: &str = "0x2c7536e3605d9c16a7a3d7b1898e529396a65c23";
+
+// hardhat's default accounts, never fund them either
+pub(crate) const ETH_MNEMONIC: &str = "test test test test test test test test test test test junk";
+pub(crate) const ETH_MNEMONIC_ADDRESSES: [&str; 2] = [
+    "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266",
+    "0x70997970c51812dc3a010c7d01b50e0d17dc79c8",
+];
+
+pub(crate) const ARWEAVE_KEYFILE: &[u8] = include_bytes!(
+    "../tests/fixtures/arweave-key-7eV1qae4qVNqsNChg3Scdi-DpOLJPCogct4ixoq1WNg.json"
+);
+pub(crate) const ARWEAVE_ADDRESS: &str = "7eV1qae4qVNqsNChg3Scdi-DpOLJPCogct4ixoq1WNg";
+
+pub(crate) const SIG_MSG: &str = "tokenSymbol:USDC\n\
+action:transfer\n\
+from:0x2c7536e3605d9c16a7a3d7b1898e529396a65c23\n\
+to:2NbYHgsuI8uQcuErDsgoRUCyj9X2wZ6PBN6WTz9xyu0\n\
+amount:1000000\n\
+fee:0\n\
+feeRecipient:0x6451eB7f668de69Fb4C943Db72bCF2A73DeeC6B1\n\
+nonce:1656000000000\n\
+tokenID:0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48\n\
+chainType:ethereum\n\
+chainID:1\n\
+data:{\"hello\":\"world\"}\n\
+version:v1";
+
+// keccak256 of SIG_MSG with the "\x19Ethereum Signed Message:\n" prefix
+pub(crate) const SIG_MSG_HASH: &str =
+    "0x7344b9f6eeb8513966a1e7238eb086cbb1ed62979daef55d6c4527a9d1b6c0fa";
+
+pub(crate) const ETH_SIGNATURE: &str = "0x8b26d411892c2e0bc7be1ffc30a1189561caf79d5b3dd9bb8cc460de18f1e88e66fd9a77736e725bc4d6c72b6953c4be81e7b86834ea22c0fdb6fa9ff9ce1e451b";
+
+// RFC 8032 ed25519 test 1, signing the empty message
+pub(crate) const ED25519_SEED: &str =
+    "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+pub(crate) const ED25519_PUBLIC_KEY: &str =
+    "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+pub(crate) const ED25519_SIGNATURE: &str = "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b";
+
+pub(crate) fn fixed_tx() -> Transaction {
+    Transaction {
+        token_symbol: "USDC".to_string(),
+        action: TX_ACTION_TRANSFER.to_string(),
+        from: ETH_ADDRESS.to_string(),
+        to: "2NbYHgsuI8uQcuErDsgoRUCyj9X2wZ6PBN6WTz9xyu0".to_string(),
+        amount: "1000000".to_string(),
+        fee: "0".to_string(),
+        fee_recipient: "0x6451eB7f668de69Fb4C943Db72bCF2A73DeeC6B1".to_string(),
+        nonce: "1656000000000".to_string(),
+        token_id: "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48".to_string(),
+        chain_type: "ethereum".to_string(),
+        chain_id: "1".to_string(),
+        data: r#"{"hello":"world"}"#.to_string(),
+        version: TX_VERSION_V1.to_string(),
+        sig: "".to_string(),
+    }
+}
+
+#[test]
+fn it_builds_sig_msg() {
+    assert_eq!(fixed_tx().sig_msg().as_bytes(), SIG_MSG.as_bytes());
+    assert_eq!(
+        format!("{:?}", ethers::utils::hash_message(SIG_MSG)),
+        SIG_MSG_HASH
+    );
+}
+
+// every field set, with values containing the separators used by the format.
+// Written by hand from our reading of everpay's Go SDK `Transaction.String()`
+// (fields in its order, `key:value` lines, nothing escaped, no trailing
+// newline). It only pins down our own format against unnoticed changes: it
+// wasn't generated with the SDK, so it can't tell whether that reading is
+// right. Compared as a whole, so that it's safe for `data` to hold the
+// separators.
+const SIG_MSG_FULL: &str = include_str!("../tests/fixtures/everpay-sig-msg-full.txt");
+
+#[test]
+fn it_keeps_sig_msg_format_with_every_field() {
+    let tx = Transaction {
+        token_symbol: "AR".to_string(),
+        action: "bundle".to_string(),
+        from: "0x2C7536E3605D9C16A7A3D7B1898E529396A65C23".to_string(),
+        to: "0x6451eB7f668de69Fb4C943Db72bCF2A73DeeC6B1".to_string(),
+        amount: "0.000000000001".to_string(),
+        fee: "0".to_string(),
+        fee_recipient: "0x6451eB7f668de69Fb4C943Db72bCF2A73DeeC6B1".to_string(),
+        nonce: "1656000000001".to_string(),
+        token_id: "arweave,ethereum-ar-AR,0x4fadc7a98f2dc96510e42dd1a74141eeae0c1543"
+            .to_string(),
+        chain_type: "arweave,ethereum".to_string(),
+        chain_id: "0,1".to_string(),
+        data: r#"{"appName":"arseeding","action":"payment","itemIds":["BewjUEppPQ9pljVrjMxF7A2Kkz5ZJt_Q7tXRkQDm2VQ"],"note":"a:b:c, \"quoted\" & ünïcödé 🚀"}"#
+            .to_string()
+            // a line break in the data isn't escaped either
+            + "\nversion:v0",
+        version: TX_VERSION_V1.to_string(),
+        // never part of the signed message
+        sig: "0xdeadbeef".to_string(),
+    };
+
+    assert_eq!(tx.sig_msg().as_bytes(), SIG_MSG_FULL.as_bytes());
+}
+
+#[tokio::test]
+async fn it_signs_with_local_eth_signer() {
+    let signer = LocalEthSigner::from_private_key(ETH_PRIVATE_KEY).unwrap();
+
+    assert_eq!(signer.wallet_address().unwrap(), ETH_ADDRESS);
+    assert_eq!(signer.sign(SIG_MSG).await.unwrap(), ETH_SIGNATURE);
+
+    let prefixed = LocalEthSigner::from_private_key(&format!("0x{}", ETH_PRIVATE_KEY)).unwrap();
+    assert_eq!(prefixed.sign(SIG_MSG).await.unwrap(), ETH_SIGNATURE);
+}
+
+#[test]
+fn it_derives_local_eth_signer_from_mnemonic() {
+    for (index, address) in ETH_MNEMONIC_ADDRESSES.iter().enumerate() {
+        let signer = LocalEthSigner::from_mnemonic(ETH_MNEMONIC, index as u32).unwrap();
+        assert_eq!(signer.wallet_address().unwrap(), *address);
+    }
+
+    assert!(LocalEthSigner::from_mnemonic("test test junk", 0).is_err());
+}
+
+// RSA-PSS signatures are salted so there's no fixed signature to compare with,
+// the signature is checked against the fixture's public key instead
+#[tokio::test]
+async fn it_signs_with_arweave_signer() {
+    let url = parse_url(DEFAULT_ARWEAVE_URL).unwrap();
+    let arweave = arweave_from_keypair_bytes(ARWEAVE_KEYFILE, url).unwrap();
+    let signer = ArweaveSigner::from_keypair_bytes(ARWEAVE_KEYFILE).unwrap();
+
+    let jwk: serde_json::Value = serde_json::from_slice(ARWEAVE_KEYFILE).unwrap();
+    let modulus = jwk["n"].as_str().unwrap();
+
+    assert_eq!(signer.wallet_address().unwrap(), ARWEAVE_ADDRESS);
+    assert_eq!(signer.owner().unwrap(), modulus);
+
+    let sig = signer.sign(SIG_MSG).await.unwrap();
+    let (sig, owner) = sig.split_once(',').unwrap();
+    assert_eq!(owner, modulus);
+
+    let sig = Base64::from_str(sig).unwrap();
+    let hash = ethers::utils::hash_message(SIG_MSG);
+    arweave.crypto.verify(&sig.0, hash.as_bytes()).unwrap();
+}
+
+fn from_hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn it_signs_with_solana_signer() {
+    let seed = from_hex(ED25519_SEED);
+    let public_key = from_hex(ED25519_PUBLIC_KEY);
+    let signer = SolanaSigner::from_seed(&seed).unwrap();
+
+    let owner = Base64(public_key.clone()).to_string();
+    assert_eq!(signer.owner().unwrap(), owner);
+    assert_eq!(
+        signer.sign("").await.unwrap(),
+        format!("{},{}", Base64(from_hex(ED25519_SIGNATURE)), owner)
+    );
+
+    let keypair = [seed, public_key].concat();
+    let from_keypair = SolanaSigner::from_keypair_bytes(&keypair).unwrap();
+    assert_eq!(
+        from_keypair.wallet_address().unwrap(),
+        signer.wallet_address().unwrap()
+    );
+    assert!(SolanaSigner::from_keypair_bytes(&keypair[..32]).is_err());
+}
@@ -0,0 +1,228 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use ring::rand::{SecureRandom, SystemRandom};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+use crate::arseeding_types::ASError;
+
+// delays between two polls: `initial`, then multiplied by `factor` after each
+// poll up to `max`. Each delay is jittered down to as little as half of it so
+// clients started together don't poll in lockstep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+    pub factor: u32,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(30),
+            factor: 2,
+        }
+    }
+}
+
+impl Backoff {
+    // delay before poll number `attempt + 1`, without jitter
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = self.factor.max(1).saturating_pow(attempt);
+        self.initial.saturating_mul(factor).min(self.max)
+    }
+
+    // `delay` scaled by `jitter`, clamped to [0.5, 1]
+    fn jittered(&self, attempt: u32, jitter: f64) -> Duration {
+        self.delay(attempt).mul_f64(jitter.clamp(0.5, 1.0))
+    }
+}
+
+fn random_jitter() -> f64 {
+    let mut b = [0u8; 2];
+    if SystemRandom::new().fill(&mut b).is_err() {
+        return 1.0;
+    }
+    0.5 + u16::from_le_bytes(b) as f64 / u16::MAX as f64 / 2.0
+}
+
+// Calls `op` until `done` accepts its result and returns that result. Errors
+// from `op` are returned right away. Fails with `Timeout` when the next poll
+// would start after `timeout`.
+pub async fn poll_until<F, Fut, T>(
+    mut op: F,
+    done: impl Fn(&T) -> bool,
+    timeout: Duration,
+    backoff: Backoff,
+) -> Result<T, ASError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, ASError>>,
+{
+    let deadline = Instant::now() + timeout;
+    let mut attempt = 0;
+    loop {
+        let res = op().await?;
+        if done(&res) {
+            return Ok(res);
+        }
+
+        let delay = backoff.jittered(attempt, random_jitter());
+        if Instant::now() + delay > deadline {
+            return Err(ASError::Timeout {
+                e: format!("condition not met after {} polls", attempt + 1),
+            });
+        }
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+// scales `value` by 10^decimals, erroring instead of truncating when the value
+// has more decimal places than the token supports
+pub fn to_base_units(symbol: &str, value: Decimal, decimals: i64) -> Result<u64, ASError> {
+    let precision_loss = || ASError::PrecisionLoss {
+        symbol: symbol.to_string(),
+        value: value.to_string(),
+    };
+
+    if value.is_sign_negative() {
+        return Err(ASError::ArgumentError {
+            arg: format!("negative amount: {}", value),
+        });
+    }
+
+    let factor = u32::try_from(decimals)
+        .ok()
+        .and_then(|d| 10u64.checked_pow(d))
+        .ok_or_else(precision_loss)?;
+    let scaled = value
+        .checked_mul(Decimal::from(factor))
+        .ok_or_else(precision_loss)?;
+
+    if !scaled.fract().is_zero() {
+        return Err(precision_loss());
+    }
+
+    scaled.to_u64().ok_or_else(precision_loss)
+}
+
+// inverse of `to_base_units`, e.g. ("1500000", 6) is 1.5
+pub fn from_base_units(amount: &str, decimals: i64) -> Result<Decimal, ASError> {
+    let invalid = || ASError::ArgumentError {
+        arg: format!("invalid base units amount: {}", amount),
+    };
+
+    let amount: i128 = amount.parse().map_err(|_| invalid())?;
+    let scale = u32::try_from(decimals).map_err(|_| invalid())?;
+
+    Decimal::try_from_i128_with_scale(amount, scale)
+        .map(|d| d.normalize())
+        .map_err(|_| invalid())
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn it_computes_backoff_delays() {
+        let b = Backoff {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(10),
+            factor: 2,
+        };
+
+        assert_eq!(b.delay(0), Duration::from_secs(1));
+        assert_eq!(b.delay(1), Duration::from_secs(2));
+        assert_eq!(b.delay(3), Duration::from_secs(8));
+        assert_eq!(b.delay(4), Duration::from_secs(10));
+        assert_eq!(b.delay(100), Duration::from_secs(10));
+
+        assert_eq!(b.jittered(1, 0.5), Duration::from_secs(1));
+        assert_eq!(b.jittered(1, 0.0), Duration::from_secs(1));
+        assert_eq!(b.jittered(1, 2.0), Duration::from_secs(2));
+
+        let j = random_jitter();
+        assert!((0.5..=1.0).contains(&j));
+    }
+
+    #[tokio::test]
+    async fn it_polls_until_done() {
+        let backoff = Backoff {
+            initial: Duration::from_millis(1),
+            max: Duration::from_millis(5),
+            factor: 2,
+        };
+
+        let mut calls = 0;
+        let res = poll_until(
+            || {
+                calls += 1;
+                let n = calls;
+                async move { Ok(n) }
+            },
+            |n| *n == 3,
+            Duration::from_secs(5),
+            backoff,
+        )
+        .await;
+        assert_eq!(res.unwrap(), 3);
+
+        let res = poll_until(
+            || async { Ok(false) },
+            |done| *done,
+            Duration::from_millis(20),
+            backoff,
+        )
+        .await;
+        assert!(matches!(res, Err(ASError::Timeout { .. })));
+
+        let res: Result<bool, ASError> = poll_until(
+            || async { Err(ASError::api_error("down")) },
+            |done| *done,
+            Duration::from_secs(5),
+            backoff,
+        )
+        .await;
+        assert!(matches!(res, Err(ASError::APIError { .. })));
+    }
+
+    #[test]
+    fn it_converts_from_base_units() {
+        let d = |s: &str| Decimal::from_str(s).unwrap();
+
+        assert_eq!(from_base_units("1500000", 6).unwrap(), d("1.5"));
+        assert_eq!(from_base_units("0", 6).unwrap(), d("0"));
+        assert_eq!(from_base_units("1", 12).unwrap(), d("0.000000000001"));
+        assert!(from_base_units("1.5", 6).is_err());
+        assert!(from_base_units("1", -1).is_err());
+    }
+
+    #[test]
+    fn it_converts_to_base_units() {
+        let d = |s: &str| Decimal::from_str(s).unwrap();
+
+        assert_eq!(to_base_units("USDC", d("1.5"), 6).unwrap(), 1_500_000);
+        assert_eq!(to_base_units("USDC", d("0.000001"), 6).unwrap(), 1);
+        assert_eq!(to_base_units("AR", d("2"), 12).unwrap(), 2_000_000_000_000);
+        assert_eq!(
+            to_base_units("USDC", d("1.50000000"), 6).unwrap(),
+            1_500_000
+        );
+
+        assert!(matches!(
+            to_base_units("USDC", d("0.0000001"), 6),
+            Err(ASError::PrecisionLoss { .. })
+        ));
+        assert!(matches!(
+            to_base_units("USDC", d("-1"), 6),
+            Err(ASError::ArgumentError { .. })
+        ));
+        assert!(to_base_units("ETH", d("100"), 18).is_err());
+    }
+}